@@ -1,8 +1,8 @@
 use {
     crate::{
-        collection::{self, Collection},
+        collection::{self, Collection, PrefixRewrite, SampleCheck},
         db::{Db, FolderChanges},
-        entry, serialization,
+        dlog, entry, root, serialization,
     },
     anyhow::{Context, bail},
     std::{
@@ -58,19 +58,78 @@ impl Application {
             .collections
             .get(&id)
             .context("No collection with such id")?;
-        let coll_dir = collections_dir_name(&self.database.data_dir);
-        let filename = collection_filename(&coll_dir, id);
-        let coll: Collection = serialization::read_from_file(&filename)
-            .with_context(move || format!("Deserialization error for: {}", filename.display()))?;
+        let mut coll = self.read_collection(id)?;
+        coll.rebuild_note_index();
         self.folder_changes_recv = Some(coll.scan_changes(path.to_owned()));
         self.active_collection = Some((id, coll));
         self.database.recent.use_(id);
         Ok(())
     }
+    pub(crate) fn read_collection(&self, id: collection::Id) -> anyhow::Result<Collection> {
+        let coll_dir = collections_dir_name(&self.database.data_dir);
+        let filename = collection_filename(&coll_dir, id);
+        serialization::read_from_file(&filename)
+            .with_context(move || format!("Deserialization error for: {}", filename.display()))
+    }
+    /// Check a sample of the entries of collection `id` against `new_root`
+    pub(crate) fn check_relocation(
+        &self,
+        id: collection::Id,
+        new_root: &Path,
+        rewrite: Option<&PrefixRewrite>,
+    ) -> anyhow::Result<SampleCheck> {
+        match &self.active_collection {
+            Some((active_id, coll)) if *active_id == id => Ok(coll.check_sample(new_root, rewrite)),
+            _ => Ok(self.read_collection(id)?.check_sample(new_root, rewrite)),
+        }
+    }
+    /// Change the root of collection `id` to `new_root`, rewriting entry paths with `rewrite`.
+    ///
+    /// Returns the number of entries rewritten.
+    pub(crate) fn relocate_collection(
+        &mut self,
+        id: collection::Id,
+        new_root: PathBuf,
+        rewrite: Option<&PrefixRewrite>,
+    ) -> anyhow::Result<usize> {
+        anyhow::ensure!(
+            self.database.collections.contains_key(&id),
+            "No collection with such id"
+        );
+        let mut n_rewritten = 0;
+        match &mut self.active_collection {
+            Some((active_id, coll)) if *active_id == id => {
+                // Change directory first, so nothing is rewritten if that fails
+                std::env::set_current_dir(&new_root).context("Failed to change to the new root")?;
+                if let Some(rewrite) = rewrite {
+                    match coll.rewrite_prefix(rewrite) {
+                        Ok(n) => n_rewritten = n,
+                        Err(e) => {
+                            let old_root = &self.database.collections[&id];
+                            if let Err(cd_err) = std::env::set_current_dir(old_root) {
+                                dlog!("Failed to change back to the old root: {cd_err}");
+                            }
+                            return Err(e);
+                        }
+                    }
+                }
+                self.folder_changes_recv = Some(coll.scan_changes(new_root.clone()));
+            }
+            _ => {
+                if let Some(rewrite) = rewrite {
+                    let mut coll = self.read_collection(id)?;
+                    n_rewritten = coll.rewrite_prefix(rewrite)?;
+                    self.save_collection(id, &coll)?;
+                }
+            }
+        }
+        self.database.collections.insert(id, new_root);
+        Ok(n_rewritten)
+    }
     pub(crate) fn apply_changes_to_active_collection(
         &mut self,
         changes: &FolderChanges,
-        callback: impl FnMut(&Path, entry::Id),
+        callback: impl FnMut(Option<root::Id>, &Path, entry::Id),
     ) {
         if let Some((_id, coll)) = self.active_collection.as_mut() {
             coll.apply_changes(changes, &mut self.database.uid_counter, callback);
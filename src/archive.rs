@@ -0,0 +1,107 @@
+//! Zip based archives (zip, cbz) as containers of entries.
+//!
+//! A file inside an archive has a virtual path like `foo.cbz!/001.png`.
+
+use {
+    crate::cbz::COMIC_INFO,
+    std::{
+        fs::File,
+        io::{self, Read as _},
+        path::{Component, Path, PathBuf},
+    },
+    zip::ZipArchive,
+};
+
+/// Separates the archive path from the path inside the archive
+const SEPARATOR: &str = "!/";
+
+const EXTENSIONS: &[&str] = &["zip", "cbz"];
+
+/// Whether `path` is an archive we can look into
+pub fn is_archive(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        EXTENSIONS
+            .iter()
+            .any(|arch_ext| ext.eq_ignore_ascii_case(arch_ext))
+    })
+}
+
+/// Split a virtual path into the archive path and the path inside the archive
+pub fn split(path: &Path) -> Option<(PathBuf, String)> {
+    let string = path.to_str()?;
+    string.match_indices(SEPARATOR).find_map(|(pos, _)| {
+        let archive = Path::new(&string[..pos]);
+        is_archive(archive).then(|| {
+            (
+                archive.to_owned(),
+                string[pos + SEPARATOR.len()..].to_owned(),
+            )
+        })
+    })
+}
+
+pub fn is_virtual(path: &Path) -> bool {
+    split(path).is_some()
+}
+
+pub fn virtual_path(archive: &Path, inner: &str) -> PathBuf {
+    let mut string = archive.as_os_str().to_owned();
+    string.push(SEPARATOR);
+    string.push(inner);
+    string.into()
+}
+
+/// Names of the files in `archive`, in archive order, leaving out junk
+pub fn list(archive: &Path) -> anyhow::Result<Vec<String>> {
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let mut names = Vec::new();
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+        if file.is_dir() {
+            continue;
+        }
+        if file
+            .enclosed_name()
+            .is_some_and(|inner| !skip_inner(&inner))
+        {
+            names.push(file.name().to_owned());
+        }
+    }
+    Ok(names)
+}
+
+/// Read the contents of a file, which may be inside an archive
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let Some((archive, inner)) = split(path) else {
+        return std::fs::read(path);
+    };
+    let mut zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+    let mut file = zip.by_name(&inner).map_err(io::Error::other)?;
+    let mut data = Vec::with_capacity(file.size().try_into().unwrap_or(0));
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Size of a file, which may be inside an archive
+pub fn size(path: &Path) -> io::Result<u64> {
+    let Some((archive, inner)) = split(path) else {
+        return Ok(std::fs::metadata(path)?.len());
+    };
+    let mut zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+    let file = zip.by_name(&inner).map_err(io::Error::other)?;
+    Ok(file.size())
+}
+
+/// Metadata and junk that archivers leave behind
+pub fn skip_inner(inner: &Path) -> bool {
+    if inner.file_name().is_some_and(|name| name == COMIC_INFO) {
+        return true;
+    }
+    inner.components().any(|comp| match comp {
+        Component::Normal(part) => {
+            let part = part.to_string_lossy();
+            part.starts_with('.') || part == "__MACOSX" || part == "Thumbs.db"
+        }
+        _ => false,
+    })
+}
@@ -0,0 +1,249 @@
+//! Comic book archives (CBZ), which are zip files of images in reading order
+
+use {
+    crate::{
+        archive::{self, skip_inner},
+        collection::{Collection, TagsExt},
+        db::UidCounter,
+        entry,
+        sequence::{self, Bookmark, Sequence},
+        tag,
+    },
+    anyhow::Context as _,
+    std::{
+        fmt::Write as _,
+        fs::File,
+        io::Write as _,
+        path::{Path, PathBuf},
+    },
+    zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions},
+};
+
+pub const COMIC_INFO: &str = "ComicInfo.xml";
+
+/// ComicInfo.xml elements that entry properties with the same name (ignoring case) are written to
+const COMIC_INFO_FIELDS: &[&str] = &[
+    "Series",
+    "Number",
+    "Volume",
+    "Summary",
+    "Year",
+    "Month",
+    "Writer",
+    "Penciller",
+    "Inker",
+    "Colorist",
+    "Letterer",
+    "CoverArtist",
+    "Editor",
+    "Publisher",
+    "Genre",
+    "Web",
+    "LanguageISO",
+];
+
+/// What to put into an exported archive
+pub struct Export {
+    pub title: String,
+    /// Entries in page order
+    pub ids: Vec<entry::Id>,
+    /// Tags written in addition to the tags of the entries, e.g. the tags of a sequence
+    pub extra_tags: Vec<tag::Id>,
+    pub bookmarks: Vec<Bookmark>,
+    /// Whether to write a ComicInfo.xml with the title, tags, notes and properties
+    pub comic_info: bool,
+}
+
+impl Export {
+    pub fn sequence(seq: &Sequence, comic_info: bool) -> Self {
+        Self {
+            title: seq.name.clone(),
+            ids: seq.entries.clone(),
+            extra_tags: seq.tags.clone(),
+            bookmarks: seq.bookmarks.clone(),
+            comic_info,
+        }
+    }
+    pub fn selection(ids: Vec<entry::Id>, comic_info: bool) -> Self {
+        Self {
+            title: String::new(),
+            ids,
+            extra_tags: Vec::new(),
+            bookmarks: Vec::new(),
+            comic_info,
+        }
+    }
+}
+
+/// Write the entries of `export` into a CBZ archive at `path`
+pub fn export(coll: &Collection, export: &Export, path: &Path) -> anyhow::Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    // Images are already compressed
+    let opts = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let width = export.ids.len().to_string().len().max(3);
+    for (i, id) in export.ids.iter().enumerate() {
+        let en = coll.entries.get(id).context("Dangling entry")?;
+        let ext = en
+            .path
+            .extension()
+            .map_or_else(String::new, |ext| format!(".{}", ext.to_string_lossy()));
+        zip.start_file(format!("{:0width$}{ext}", i + 1), opts)?;
+        let data = archive::read(&coll.entry_path(en))
+            .with_context(|| format!("Failed to read {}", en.path.display()))?;
+        zip.write_all(&data)?;
+    }
+    if export.comic_info {
+        zip.start_file(COMIC_INFO, SimpleFileOptions::default())?;
+        zip.write_all(comic_info(coll, export).as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn comic_info(coll: &Collection, export: &Export) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ComicInfo xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\">\n",
+    );
+    let mut element = |name: &str, value: &str| {
+        let _ = writeln!(xml, "  <{name}>{}</{name}>", escape(value));
+    };
+    if !export.title.is_empty() {
+        element("Title", &export.title);
+    }
+    let entries: Vec<_> = export
+        .ids
+        .iter()
+        .filter_map(|id| coll.entries.get(id))
+        .collect();
+    element("PageCount", &entries.len().to_string());
+    let mut tags: Vec<_> = entries
+        .iter()
+        .flat_map(|en| en.tags.iter())
+        .chain(&export.extra_tags)
+        .map(|id| coll.tags.first_name_of(id))
+        .collect();
+    tags.sort();
+    tags.dedup();
+    if !tags.is_empty() {
+        element("Tags", &tags.join(", "));
+    }
+    let notes: Vec<_> = entries
+        .iter()
+        .map(|en| en.notes.trim())
+        .filter(|notes| !notes.is_empty())
+        .collect();
+    if !notes.is_empty() {
+        element("Notes", &notes.join("\n\n"));
+    }
+    // Only properties that every page agrees on describe the whole book
+    for field in COMIC_INFO_FIELDS {
+        let mut values = entries.iter().map(|en| {
+            en.props
+                .iter()
+                .find_map(|(k, v)| k.eq_ignore_ascii_case(field).then_some(v.as_str()))
+        });
+        if let Some(Some(first)) = values.next()
+            && !first.is_empty()
+            && values.all(|val| val == Some(first))
+        {
+            element(field, first);
+        }
+    }
+    if !export.bookmarks.is_empty() {
+        xml.push_str("  <Pages>\n");
+        for bm in &export.bookmarks {
+            if let Some(pos) = export.ids.iter().position(|id| *id == bm.entry) {
+                let _ = writeln!(
+                    xml,
+                    "    <Page Image=\"{pos}\" Bookmark=\"{}\"/>",
+                    escape(&bm.name)
+                );
+            }
+        }
+        xml.push_str("  </Pages>\n");
+    }
+    xml.push_str("</ComicInfo>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Extract `archive` into a new directory under `root`, add the files as entries,
+/// and make a sequence of them in archive order.
+///
+/// Returns the new sequence, and the directory the files were extracted to, relative to `root`.
+pub fn import(
+    coll: &mut Collection,
+    archive: &Path,
+    root: &Path,
+    uid_counter: &mut UidCounter,
+) -> anyhow::Result<(sequence::Id, PathBuf)> {
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let name = archive.file_stem().map_or_else(
+        || "Imported".into(),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+    let dir = unique_dir(root, &name);
+    let mut paths = Vec::new();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        if file.is_dir() {
+            continue;
+        }
+        let Some(inner) = file.enclosed_name() else {
+            continue;
+        };
+        let ignored_ext = inner.extension().is_some_and(|ext| {
+            coll.ignored_extensions
+                .iter()
+                .any(|ign_ext| ext == ign_ext.as_str())
+        });
+        if ignored_ext || skip_inner(&inner) {
+            continue;
+        }
+        let rel = dir.join(inner);
+        let abs = root.join(&rel);
+        if let Some(parent) = abs.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut file, &mut File::create(&abs)?)?;
+        paths.push(rel);
+    }
+    anyhow::ensure!(!paths.is_empty(), "The archive doesn't contain any files");
+    let ids: Vec<_> = paths
+        .into_iter()
+        .map(|path| coll.add_new_entry(None, path, uid_counter))
+        .collect();
+    let seq = coll.add_new_sequence(&name, uid_counter);
+    coll.sequences
+        .get_mut(&seq)
+        .context("New sequence disappeared")?
+        .entries = ids;
+    Ok((seq, dir))
+}
+
+/// `name`, or `name (2)`, `name (3)`... whichever doesn't exist under `root` yet
+fn unique_dir(root: &Path, name: &str) -> PathBuf {
+    let mut dir = PathBuf::from(name);
+    let mut n = 2;
+    while root.join(&dir).exists() {
+        dir = PathBuf::from(format!("{name} ({n})"));
+        n += 1;
+    }
+    dir
+}
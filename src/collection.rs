@@ -1,20 +1,28 @@
 use {
     crate::{
+        archive,
+        constraints::Rules,
         db::{EntryMap, EntrySet, FolderChanges, Uid, UidCounter},
         dlog,
         entry::{self, Entry},
+        entry_utils::natural_path_cmp,
         filter_reqs::Requirements,
         folder_scan::walkdir,
-        gui::SelectionBufs,
+        notes::NoteIndex,
         preferences,
+        relation::{self, Relation, Relations},
+        root::{self, Root},
+        selection::SelectionBufs,
         sequence::{self, Sequence},
         tag::{self, Tag},
+        tag_stats::implied_closure,
     },
     anyhow::{Context, bail},
     fnv::FnvHashMap,
     serde_derive::{Deserialize, Serialize},
     std::{
         borrow::Cow,
+        collections::{BTreeMap, HashMap, HashSet},
         ffi::OsStr,
         path::{Path, PathBuf},
         sync::mpsc::Receiver,
@@ -26,6 +34,10 @@ pub type Entries = EntryMap<Entry>;
 pub type Tags = FnvHashMap<tag::Id, Tag>;
 pub type Sequences = FnvHashMap<sequence::Id, Sequence>;
 pub type TagSpecificApps = FnvHashMap<tag::Id, preferences::AppId>;
+/// Tags to add to or remove from each entry
+pub type TagChanges = Vec<(entry::Id, Vec<tag::Id>)>;
+/// Keys (`1`-`9`, `a`-`z`) bound to tags for rapid tagging
+pub type TagHotkeys = BTreeMap<char, tag::Id>;
 
 pub trait TagsExt {
     fn first_name_of(&'_ self, id: &tag::Id) -> Cow<'_, str>;
@@ -42,7 +54,7 @@ impl TagsExt for Tags {
 
 /// A collection of entries.
 ///
-/// Each collection has a root that all the entries stem from.
+/// Each collection has a primary root that the entries stem from, and can have additional roots.
 #[derive(Serialize, Deserialize)]
 pub struct Collection {
     /// List of entries
@@ -55,6 +67,32 @@ pub struct Collection {
     /// Extensions that are ignored when updating from folder contents
     #[serde(default)]
     pub ignored_extensions: Vec<String>,
+    /// Named selection buffers, which also act as unordered albums
+    #[serde(default)]
+    pub sel_bufs: SelectionBufs,
+    /// Keys that toggle a tag while rapid tagging
+    #[serde(default)]
+    pub tag_hotkeys: TagHotkeys,
+    /// Constraints that the tags of entries should satisfy
+    #[serde(default)]
+    pub rules: Rules,
+    /// Typed links between entries
+    #[serde(default)]
+    pub relations: Relations,
+    /// Names of the string properties entries can have
+    #[serde(default)]
+    pub prop_schema: Vec<String>,
+    /// Look into zip/cbz archives when scanning, and add the files inside them as entries
+    #[serde(default)]
+    pub browse_archives: bool,
+    /// Additional roots. The primary root is kept in the database.
+    #[serde(default)]
+    pub roots: Vec<Root>,
+    #[serde(skip)]
+    pub(crate) note_index: NoteIndex,
+    /// Bumped when the tags of entries or the implications of tags change
+    #[serde(skip)]
+    tags_revision: u64,
 }
 
 #[derive(Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug)]
@@ -68,6 +106,15 @@ impl Collection {
             sequences: Sequences::default(),
             tag_specific_apps: TagSpecificApps::default(),
             ignored_extensions: Vec::new(),
+            sel_bufs: SelectionBufs::new(),
+            tag_hotkeys: TagHotkeys::new(),
+            rules: Rules::new(),
+            relations: Relations::new(),
+            prop_schema: Vec::new(),
+            browse_archives: false,
+            roots: Vec::new(),
+            note_index: NoteIndex::default(),
+            tags_revision: 0,
         };
         coll.update_from_paths(uid_counter, paths);
         coll
@@ -101,16 +148,50 @@ impl Collection {
             }
             keep
         });
+        self.prune_relations();
     }
     pub fn add_tag_for(&mut self, entry: entry::Id, tag: tag::Id) -> Result<(), AddTagError> {
         match self.entries.get_mut(&entry) {
             Some(en) => {
                 en.tags.insert(tag);
+                self.tags_revision += 1;
                 Ok(())
             }
             None => Err(AddTagError),
         }
     }
+    /// Set the star rating of `ids`. 0 clears the rating.
+    pub fn set_rating(&mut self, ids: &[entry::Id], rating: u8) {
+        for id in ids {
+            if let Some(en) = self.entries.get_mut(id) {
+                en.rating = rating.min(5);
+            }
+        }
+    }
+    /// Make all of `ids` favorites, or if they all are already, unfavorite them
+    pub fn toggle_favorite(&mut self, ids: &[entry::Id]) {
+        let all_fav = ids
+            .iter()
+            .all(|id| self.entries.get(id).is_some_and(|en| en.favorite));
+        for id in ids {
+            if let Some(en) = self.entries.get_mut(id) {
+                en.favorite = !all_fav;
+            }
+        }
+    }
+    /// Add `tag` to `entry` if it doesn't have it yet, and set its value
+    pub fn set_tag_value(
+        &mut self,
+        entry: entry::Id,
+        tag: tag::Id,
+        value: f64,
+    ) -> Result<(), AddTagError> {
+        let en = self.entries.get_mut(&entry).ok_or(AddTagError)?;
+        en.tags.insert(tag);
+        en.tag_values.insert(tag, value);
+        self.tags_revision += 1;
+        Ok(())
+    }
     pub fn add_tag_for_multi(
         &mut self,
         entries: &[entry::Id],
@@ -121,9 +202,10 @@ impl Collection {
         }
         Ok(())
     }
-    fn add_new_tag(&mut self, tag: Tag, uid_counter: &mut UidCounter) -> tag::Id {
+    pub(crate) fn add_new_tag(&mut self, tag: Tag, uid_counter: &mut UidCounter) -> tag::Id {
         let uid = tag::Id(uid_counter.next());
         self.tags.insert(uid, tag);
+        self.tags_revision += 1;
         uid
     }
     /// Returns `None` if said tag already exists
@@ -145,18 +227,61 @@ impl Collection {
             uid_counter,
         ))
     }
-    pub fn filter<'a>(
-        &'a self,
-        reqs: &'a Requirements,
-        sel_bufs: &'a SelectionBufs,
-    ) -> impl Iterator<Item = entry::Id> + 'a {
-        self.entries.iter().filter_map(move |(&uid, en)| {
-            entry::filter_map(uid, en, reqs, &self.tags, &self.sequences, sel_bufs)
-        })
+    pub fn filter<'a>(&'a self, reqs: &'a Requirements) -> impl Iterator<Item = entry::Id> + 'a {
+        // Top level note searches can narrow down the entries to check using the note index
+        let candidates = reqs
+            .note_queries()
+            .filter_map(|query| self.note_index.candidates(query))
+            .reduce(|prev, ids| prev.intersection(&ids).copied().collect());
+        let inherited = self.inherited_tag_map();
+        self.entries
+            .iter()
+            .filter(move |(uid, _)| candidates.as_ref().is_none_or(|ids| ids.contains(uid)))
+            .filter_map(move |(&uid, en)| {
+                let inherited = inherited.get(&uid).map_or(&[][..], Vec::as_slice);
+                entry::filter_map(uid, en, reqs, self, inherited)
+            })
+    }
+    pub(crate) fn set_notes(&mut self, id: entry::Id, notes: String) {
+        if let Some(en) = self.entries.get_mut(&id) {
+            self.note_index.update(id, &notes);
+            en.notes = notes;
+        }
+    }
+    /// Rename a property in the schema and in every entry
+    pub(crate) fn rename_prop(&mut self, old: &str, new: &str) -> anyhow::Result<()> {
+        if self.prop_schema.iter().any(|name| name == new) {
+            bail!("There is already a property named {new}");
+        }
+        let name = self
+            .prop_schema
+            .iter_mut()
+            .find(|name| *name == old)
+            .context("No such property")?;
+        new.clone_into(name);
+        for en in self.entries.values_mut() {
+            if let Some(val) = en.props.remove(old) {
+                en.props.insert(new.to_owned(), val);
+            }
+        }
+        Ok(())
+    }
+    /// Remove a property from the schema, along with its values
+    pub(crate) fn remove_prop(&mut self, name: &str) {
+        self.prop_schema.retain(|prop| prop != name);
+        for en in self.entries.values_mut() {
+            en.props.remove(name);
+        }
+    }
+    /// Build the note index, which isn't serialized
+    pub(crate) fn rebuild_note_index(&mut self) {
+        self.note_index = NoteIndex::build(&self.entries);
     }
     pub fn rename(&mut self, uid: entry::Id, new: &str) -> anyhow::Result<()> {
+        let en = self.entries.get(&uid).context("Couldn't get entry")?;
+        let root = self.resolve_path(en.root, Path::new("")).into_owned();
         let en = self.entries.get_mut(&uid).context("Couldn't get entry")?;
-        pathbuf_rename_filename(&mut en.path, new)?;
+        pathbuf_rename_filename(&root, &mut en.path, new)?;
         Ok(())
     }
 
@@ -170,9 +295,15 @@ impl Collection {
     }
 
     pub fn remove_tags(&mut self, tags_to_del: &[tag::Id]) {
+        self.tags_revision += 1;
         self.tags.retain(|uid, _| {
             if tags_to_del.contains(uid) {
                 cleanse_tag_from_entries(&mut self.entries, *uid);
+                self.tag_hotkeys.retain(|_, tag| tag != uid);
+                self.rules.retain(|rule| !rule.refers_to(*uid));
+                for seq in self.sequences.values_mut() {
+                    seq.tags.retain(|tag| tag != uid);
+                }
                 false
             } else {
                 true
@@ -189,6 +320,28 @@ impl Collection {
         self.sequences.insert(uid, Sequence::new_with_name(name));
         uid
     }
+    /// Tags that `id` inherits from the sequences it's part of, along with the sequence
+    pub(crate) fn inherited_tags(
+        &self,
+        id: entry::Id,
+    ) -> impl Iterator<Item = (tag::Id, sequence::Id)> + '_ {
+        self.sequences
+            .iter()
+            .filter(move |(_, seq)| !seq.tags.is_empty() && seq.contains_entry(id))
+            .flat_map(|(seq_id, seq)| seq.tags.iter().map(move |tag| (*tag, *seq_id)))
+    }
+    /// Tags that each entry inherits from the sequences it's part of.
+    ///
+    /// Entries that don't inherit anything are left out.
+    pub(crate) fn inherited_tag_map(&self) -> EntryMap<Vec<tag::Id>> {
+        let mut map: EntryMap<Vec<tag::Id>> = EntryMap::default();
+        for seq in self.sequences.values().filter(|seq| !seq.tags.is_empty()) {
+            for id in &seq.entries {
+                map.entry(*id).or_default().extend(seq.tags.iter().copied());
+            }
+        }
+        map
+    }
     /// Adds the specified entries to the specified sequence
     ///
     /// # Panics
@@ -197,9 +350,78 @@ impl Collection {
     pub(crate) fn add_entries_to_sequence(&mut self, seq: sequence::Id, entries: &[entry::Id]) {
         // Do a default filename based sorting before adding
         let mut sorted = entries.to_owned();
-        sorted.sort_by_key(|id| self.entries.get(id).map(|en| &en.path));
+        self.sort_naturally(&mut sorted);
         self.sequences.get_mut(&seq).unwrap().entries.extend(sorted);
     }
+    /// Sort entry ids by the natural order of their paths
+    pub(crate) fn sort_naturally(&self, ids: &mut [entry::Id]) {
+        ids.sort_by(|a, b| match (self.entries.get(a), self.entries.get(b)) {
+            (Some(a), Some(b)) => natural_path_cmp(&a.path, &b.path),
+            (a, b) => a.is_some().cmp(&b.is_some()).reverse(),
+        });
+    }
+    /// Move the entries of `seq` from index `at` onwards into a new sequence
+    pub(crate) fn split_sequence(
+        &mut self,
+        seq: sequence::Id,
+        at: usize,
+        uid_counter: &mut UidCounter,
+    ) -> anyhow::Result<sequence::Id> {
+        let old = self.sequences.get_mut(&seq).context("No such sequence")?;
+        if at == 0 || at >= old.entries.len() {
+            bail!("Can't split a sequence at its ends");
+        }
+        let tail = old.entries.split_off(at);
+        // Bookmarks and progress follow the entries they point at
+        let (tail_bookmarks, bookmarks) = std::mem::take(&mut old.bookmarks)
+            .into_iter()
+            .partition(|bm| tail.contains(&bm.entry));
+        old.bookmarks = bookmarks;
+        let tail_progress = old.progress.take_if(|id| tail.contains(id));
+        let tail_cover = old.cover.take_if(|id| tail.contains(id));
+        let tags = old.tags.clone();
+        let name = format!("{} (split)", old.name);
+        let new = self.add_new_sequence(&name, uid_counter);
+        let new_seq = self
+            .sequences
+            .get_mut(&new)
+            .context("New sequence disappeared")?;
+        new_seq.entries = tail;
+        new_seq.bookmarks = tail_bookmarks;
+        new_seq.progress = tail_progress;
+        // Both halves are still part of the same series
+        new_seq.tags = tags;
+        new_seq.cover = tail_cover;
+        Ok(new)
+    }
+    /// Append the entries, bookmarks and tags of `from` to `into`, and delete `from`
+    pub(crate) fn merge_sequences(
+        &mut self,
+        into: sequence::Id,
+        from: sequence::Id,
+    ) -> anyhow::Result<()> {
+        if into == from {
+            bail!("Can't merge a sequence into itself");
+        }
+        let from = self
+            .sequences
+            .remove(&from)
+            .context("No sequence to merge")?;
+        let into = self
+            .sequences
+            .get_mut(&into)
+            .context("No sequence to merge into")?;
+        into.entries.extend(from.entries);
+        into.dedupe();
+        into.bookmarks.extend(from.bookmarks);
+        into.progress = into.progress.or(from.progress);
+        for tag in from.tags {
+            if !into.tags.contains(&tag) {
+                into.tags.push(tag);
+            }
+        }
+        Ok(())
+    }
 
     pub(crate) fn find_related_sequences(&self, ids: &[entry::Id]) -> Vec<sequence::Id> {
         self.related_seqs_of(ids).collect()
@@ -218,22 +440,51 @@ impl Collection {
         })
     }
 
-    pub(crate) fn get_first_related_sequence_of(&self, id: entry::Id) -> Option<&Sequence> {
+    pub(crate) fn get_first_related_sequence_of(
+        &self,
+        id: entry::Id,
+    ) -> Option<(sequence::Id, &Sequence)> {
         self.related_seqs_of(&[id])
             .next()
-            .and_then(|id| self.sequences.get(&id))
+            .and_then(|id| Some((id, self.sequences.get(&id)?)))
     }
 
-    pub(crate) fn scan_changes(&self, root: PathBuf) -> Receiver<anyhow::Result<FolderChanges>> {
-        let paths = self
-            .entries
-            .values()
-            .map(|en| en.path.to_path_buf())
-            .collect::<Vec<_>>();
+    /// Scan the primary root (`primary`) and the additional roots for changes
+    pub(crate) fn scan_changes(&self, primary: PathBuf) -> Receiver<anyhow::Result<FolderChanges>> {
+        let mut roots = vec![(None, primary)];
+        roots.extend(
+            self.roots
+                .iter()
+                .map(|root| (Some(root.id), root.path.clone())),
+        );
+        let mut paths: HashMap<Option<root::Id>, Vec<PathBuf>> = HashMap::new();
+        for en in self.entries.values() {
+            paths.entry(en.root).or_default().push(en.path.clone());
+        }
         let ign_ext = self.ignored_extensions.clone();
+        let browse_archives = self.browse_archives;
         let (send, recv) = std::sync::mpsc::channel();
         std::thread::spawn(move || {
-            let changes = scan_changes(&root, paths, &ign_ext);
+            let changes = try {
+                let mut changes = FolderChanges::default();
+                for (id, path) in roots {
+                    // Don't take the entries of a missing root as removed
+                    if !path.is_dir() {
+                        changes.unavailable.push(id);
+                        continue;
+                    }
+                    let root_paths = paths.remove(&id).unwrap_or_default();
+                    scan_root(
+                        &mut changes,
+                        id,
+                        &path,
+                        root_paths,
+                        &ign_ext,
+                        browse_archives,
+                    )?;
+                }
+                changes
+            };
             if let Err(e) = send.send(changes) {
                 dlog!("Failed to send folder changes: {e}");
             }
@@ -245,19 +496,196 @@ impl Collection {
         &mut self,
         changes: &FolderChanges,
         uid_counter: &mut UidCounter,
-        mut callback: impl FnMut(&Path, entry::Id),
+        mut callback: impl FnMut(Option<root::Id>, &Path, entry::Id),
     ) {
-        for path in &changes.add {
-            let id = self.add_new_entry(path.clone(), uid_counter);
-            callback(path, id);
+        for (root, path) in &changes.add {
+            let id = self.add_new_entry(*root, path.clone(), uid_counter);
+            callback(*root, path, id);
         }
-        self.entries
-            .retain(|_k, en| !changes.remove.contains(&en.path));
+        self.entries.retain(|_k, en| {
+            !changes
+                .remove
+                .iter()
+                .any(|(root, path)| *root == en.root && *path == en.path)
+        });
+        self.prune_sel_bufs();
+        self.prune_relations();
+        self.tags_revision += 1;
+    }
+    pub(crate) fn root(&self, id: root::Id) -> Option<&Root> {
+        self.roots.iter().find(|root| root.id == id)
+    }
+    /// Name of a root, where `None` is the primary root
+    pub(crate) fn root_name(&self, id: Option<root::Id>) -> Cow<'_, str> {
+        match id {
+            None => root::PRIMARY_NAME.into(),
+            Some(id) => match self.root(id) {
+                Some(root) => root.name.as_str().into(),
+                None => format!("<dangling:{id:?}>").into(),
+            },
+        }
+    }
+    /// Path to access `path` of `root` with
+    pub(crate) fn resolve_path<'p>(&self, root: Option<root::Id>, path: &'p Path) -> Cow<'p, Path> {
+        root::resolve(&self.roots, root, path)
+    }
+    /// Path to access the file of `en` with
+    pub(crate) fn entry_path<'en>(&self, en: &'en Entry) -> Cow<'en, Path> {
+        self.resolve_path(en.root, &en.path)
+    }
+    pub(crate) fn add_root(
+        &mut self,
+        name: String,
+        path: PathBuf,
+        uid_counter: &mut UidCounter,
+    ) -> root::Id {
+        let id = root::Id(uid_counter.next());
+        self.roots.push(Root { id, name, path });
+        id
+    }
+    /// Check whether the files of a sample of the primary root entries exist under `root`,
+    /// with their paths rewritten by `rewrite`
+    pub(crate) fn check_sample(&self, root: &Path, rewrite: Option<&PrefixRewrite>) -> SampleCheck {
+        let paths: Vec<&Path> = self
+            .entries
+            .values()
+            .filter(|en| en.root.is_none())
+            .map(|en| en.path.as_path())
+            .collect();
+        let step = paths.len() / SampleCheck::SIZE + 1;
+        let mut check = SampleCheck::default();
+        for path in paths.into_iter().step_by(step) {
+            let path = rewrite
+                .and_then(|rw| rw.apply(path))
+                .unwrap_or_else(|| path.to_owned());
+            // For a file inside an archive, the archive standing in the right place is enough
+            let file = archive::split(&path).map_or_else(|| path.clone(), |(arch, _)| arch);
+            check.checked += 1;
+            if root.join(file).exists() {
+                check.found += 1;
+            } else {
+                check.missing.push(path);
+            }
+        }
+        check
+    }
+    /// Rewrite the paths of the primary root entries that start with the prefix of `rewrite`.
+    ///
+    /// Returns the number of entries rewritten.
+    pub(crate) fn rewrite_prefix(&mut self, rewrite: &PrefixRewrite) -> anyhow::Result<usize> {
+        let new_paths: Vec<(entry::Id, PathBuf)> = self
+            .entries
+            .iter()
+            .filter(|(_, en)| en.root.is_none())
+            .filter_map(|(id, en)| Some((*id, rewrite.apply(&en.path)?)))
+            .collect();
+        // Check for clashes first, so we don't leave things half done
+        let moved: EntrySet = new_paths.iter().map(|(id, _)| *id).collect();
+        let mut taken: HashSet<&Path> = self
+            .entries
+            .iter()
+            .filter(|(id, en)| en.root.is_none() && !moved.contains(id))
+            .map(|(_, en)| en.path.as_path())
+            .collect();
+        for (_, new) in &new_paths {
+            if !taken.insert(new) {
+                bail!("{} would clash with another entry", new.display());
+            }
+        }
+        for (id, new) in &new_paths {
+            if let Some(en) = self.entries.get_mut(id) {
+                en.path.clone_from(new);
+            }
+        }
+        Ok(new_paths.len())
+    }
+    /// Remove a root along with its entries. The files are left alone.
+    pub(crate) fn remove_root(&mut self, id: root::Id) {
+        self.roots.retain(|root| root.id != id);
+        self.entries.retain(|_k, en| en.root != Some(id));
+        self.prune_sel_bufs();
+        self.prune_relations();
+        self.prune_sequences();
+        self.tags_revision += 1;
+    }
+    /// Remove relations that refer to entries that no longer exist
+    pub(crate) fn prune_relations(&mut self) {
+        self.relations.retain(|rel| {
+            self.entries.contains_key(&rel.from) && self.entries.contains_key(&rel.to)
+        });
+    }
+    /// Link `from` to `to`. Returns false if they're the same entry, or already linked this way.
+    pub(crate) fn add_relation(
+        &mut self,
+        from: entry::Id,
+        kind: relation::Kind,
+        to: entry::Id,
+    ) -> bool {
+        let new = Relation { from, kind, to };
+        if from == to || self.relations.iter().any(|rel| rel.same_as(&new)) {
+            return false;
+        }
+        self.relations.push(new);
+        true
+    }
+    pub(crate) fn relations_of(&self, id: entry::Id) -> impl Iterator<Item = &Relation> {
+        self.relations.iter().filter(move |rel| rel.involves(id))
+    }
+    /// Every entry linked to `id` through variant relations, directly or indirectly,
+    /// including `id` itself. Sorted by id.
+    pub(crate) fn variants_of(&self, id: entry::Id) -> Vec<entry::Id> {
+        let mut group = vec![id];
+        let mut i = 0;
+        while let Some(&cur) = group.get(i) {
+            for rel in self.relations_of(cur) {
+                if rel.kind == relation::Kind::VariantOf
+                    && let Some(other) = rel.other(cur)
+                    && !group.contains(&other)
+                {
+                    group.push(other);
+                }
+            }
+            i += 1;
+        }
+        group.sort_by_key(|id| id.0);
+        group
+    }
+    /// Counter that changes whenever the tags of entries or the implications of tags do,
+    /// for caches of tag derived data
+    pub(crate) fn tags_revision(&self) -> u64 {
+        self.tags_revision
+    }
+    /// Note a change to the tags of entries or the implications of tags made directly
+    /// through the fields
+    pub(crate) fn tags_changed(&mut self) {
+        self.tags_revision += 1;
+    }
+    /// Remove ids from the sequences that no longer refer to an entry
+    pub(crate) fn prune_sequences(&mut self) {
+        let exists = |id: &entry::Id| self.entries.contains_key(id);
+        for seq in self.sequences.values_mut() {
+            seq.entries.retain(exists);
+            seq.cover = seq.cover.filter(exists);
+            seq.progress = seq.progress.filter(exists);
+            seq.bookmarks.retain(|bm| exists(&bm.entry));
+        }
+    }
+    /// Remove ids from the selection buffers that no longer refer to an entry
+    pub(crate) fn prune_sel_bufs(&mut self) {
+        self.sel_bufs
+            .for_each_mut(|buf| buf.buf.retain(|id| self.entries.contains_key(id)));
     }
 
-    fn add_new_entry(&mut self, path: PathBuf, uid_counter: &mut UidCounter) -> entry::Id {
+    pub(crate) fn add_new_entry(
+        &mut self,
+        root: Option<root::Id>,
+        path: PathBuf,
+        uid_counter: &mut UidCounter,
+    ) -> entry::Id {
         let uid = entry::Id(uid_counter.next());
-        self.entries.insert(uid, Entry::new(path));
+        let mut en = Entry::new(path);
+        en.root = root;
+        self.entries.insert(uid, en);
         uid
     }
     /// Check if we have the specific text as a tag name in the tag database
@@ -283,7 +711,118 @@ impl Collection {
         self.tags.remove(&merge);
         Ok(())
     }
+    /// Tags that `ids` only have through implications, and would need to have added to have
+    /// them explicitly
+    pub(crate) fn implied_tags_to_add(&self, ids: &[entry::Id]) -> TagChanges {
+        ids.iter()
+            .filter_map(|id| {
+                let en = self.entries.get(id)?;
+                let mut add: Vec<_> = implied_closure(en.tags.iter().copied(), &self.tags)
+                    .into_iter()
+                    .filter(|tag| !en.tags.contains(tag) && self.tags.contains_key(tag))
+                    .collect();
+                add.sort_by_key(|tag| tag.0);
+                (!add.is_empty()).then_some((*id, add))
+            })
+            .collect()
+    }
+    /// Explicit tags of `ids` that another tag of the same entry already implies.
+    ///
+    /// Tags that imply each other in a cycle are not all removed, one of them is kept.
+    /// Tags with a value are kept too, as implied tags can't carry one.
+    pub(crate) fn redundant_tags(&self, ids: &[entry::Id]) -> TagChanges {
+        ids.iter()
+            .filter_map(|id| {
+                let en = self.entries.get(id)?;
+                let mut kept: Vec<_> = en.tags.iter().copied().collect();
+                kept.sort_by_key(|tag| tag.0);
+                let mut remove = Vec::new();
+                for tag in kept.clone() {
+                    if en.tag_values.contains_key(&tag) {
+                        continue;
+                    }
+                    let implied_by_others = implied_closure(
+                        kept.iter()
+                            .filter(|other| **other != tag)
+                            .filter_map(|other| self.tags.get(other))
+                            .flat_map(|other| other.implies.iter().copied()),
+                        &self.tags,
+                    );
+                    if implied_by_others.contains(&tag) {
+                        kept.retain(|other| *other != tag);
+                        remove.push(tag);
+                    }
+                }
+                (!remove.is_empty()).then_some((*id, remove))
+            })
+            .collect()
+    }
+    pub(crate) fn add_tag_changes(&mut self, changes: &TagChanges) {
+        self.tags_revision += 1;
+        for (id, tags) in changes {
+            if let Some(en) = self.entries.get_mut(id) {
+                en.tags.extend(tags.iter().copied());
+            }
+        }
+    }
+    pub(crate) fn remove_tag_changes(&mut self, changes: &TagChanges) {
+        self.tags_revision += 1;
+        for (id, tags) in changes {
+            if let Some(en) = self.entries.get_mut(id) {
+                for tag in tags {
+                    en.remove_tag(*tag);
+                }
+            }
+        }
+    }
+    /// Split a new tag off of `tag`. The inverse of [`Self::merge_tags`].
+    ///
+    /// 1. Create a new tag named `new_names`, with the same implications as `tag`
+    /// 2. Remove any of `new_names` that are aliases of `tag` from it
+    /// 3. Replace `tag` with the new tag on `entries`
+    pub(crate) fn split_tag(
+        &mut self,
+        tag: tag::Id,
+        new_names: Vec<String>,
+        entries: &[entry::Id],
+        uid_counter: &mut UidCounter,
+    ) -> anyhow::Result<tag::Id> {
+        if new_names.is_empty() {
+            bail!("The new tag needs at least one name");
+        }
+        let old = self.tags.get(&tag).context("Couldn't get tag to split")?;
+        if old.names.iter().all(|name| new_names.contains(name)) {
+            bail!("The original tag needs to keep at least one name");
+        }
+        if let Some(taken) = new_names
+            .iter()
+            .find(|name| !old.names.contains(name) && self.has_text_as_tag_name(name))
+        {
+            bail!("There is already a tag named {taken}");
+        }
+        let implies = old.implies.clone();
+        if let Some(old) = self.tags.get_mut(&tag) {
+            old.names.retain(|name| !new_names.contains(name));
+        }
+        let new = self.add_new_tag(
+            Tag {
+                names: new_names,
+                implies,
+            },
+            uid_counter,
+        );
+        for id in entries {
+            if let Some(en) = self.entries.get_mut(id)
+                && en.tags.contains(&tag)
+            {
+                en.replace_tag(tag, new);
+            }
+        }
+        self.tags_revision += 1;
+        Ok(new)
+    }
     fn replace_tag_refs(&mut self, replace: tag::Id, with: tag::Id) {
+        self.tags_revision += 1;
         // Entries
         for en in self.entries.values_mut() {
             en.replace_tag(replace, with);
@@ -292,29 +831,56 @@ impl Collection {
         for tag in self.tags.values_mut() {
             tag.replace_imply(replace, with);
         }
+        // Hotkeys
+        for tag in self.tag_hotkeys.values_mut() {
+            if *tag == replace {
+                *tag = with;
+            }
+        }
+        // Rules
+        for rule in &mut self.rules {
+            rule.replace_tag(replace, with);
+        }
+        // Merging can make a rule refer to the same tag twice, or duplicate another rule
+        let mut kept = Rules::new();
+        for rule in self.rules.drain(..) {
+            if !rule.is_degenerate() && !kept.contains(&rule) {
+                kept.push(rule);
+            }
+        }
+        self.rules = kept;
+        // Sequence tags
+        for seq in self.sequences.values_mut() {
+            seq.replace_tag(replace, with);
+        }
     }
 }
 
-pub fn scan_changes(
+/// Scan a single root for changes, adding them to `changes`
+pub fn scan_root(
+    changes: &mut FolderChanges,
+    root_id: Option<root::Id>,
     root: &Path,
     coll_paths: Vec<PathBuf>,
     ignored_extensions: &[String],
-) -> anyhow::Result<FolderChanges> {
+    browse_archives: bool,
+) -> anyhow::Result<()> {
     let wd = walkdir(root);
-    let mut add = Vec::new();
-    let mut remove = Vec::new();
+    let FolderChanges { add, remove, .. } = changes;
+    let ignored = |path: &Path| {
+        path.extension().is_some_and(|ext| {
+            ignored_extensions
+                .iter()
+                .any(|ign_ext| ext == AsRef::<OsStr>::as_ref(ign_ext))
+        })
+    };
     // Scan for additions (paths we don't have)
     for dir_entry in wd {
         let dir_entry = dir_entry?;
         if dir_entry.file_type().is_dir() {
             continue;
         }
-        let ignored_ext = dir_entry.path().extension().is_some_and(|ext| {
-            ignored_extensions
-                .iter()
-                .any(|ign_ext| ext == AsRef::<OsStr>::as_ref(ign_ext))
-        });
-        if ignored_ext {
+        if ignored(dir_entry.path()) {
             continue;
         }
         let dir_entry_path = dir_entry.into_path();
@@ -325,17 +891,75 @@ pub fn scan_changes(
                 continue;
             }
         };
+        if browse_archives && archive::is_archive(dir_entry_path) {
+            let names = match archive::list(&root.join(dir_entry_path)) {
+                Ok(names) => names,
+                Err(e) => {
+                    eprintln!("Failed to list archive {dir_entry_path:?}: {e}");
+                    continue;
+                }
+            };
+            for name in names {
+                let path = archive::virtual_path(dir_entry_path, &name);
+                if !ignored(&path) && !coll_paths.contains(&path) {
+                    add.push((root_id, path));
+                }
+            }
+            continue;
+        }
         if !coll_paths.iter().any(|p| p == dir_entry_path) {
-            add.push(dir_entry_path.to_owned());
+            add.push((root_id, dir_entry_path.to_owned()));
         }
     }
     // Scan for removes (paths we have but fs doesn't have)
+    let mut listings: HashMap<PathBuf, HashSet<String>> = HashMap::new();
     for path in coll_paths {
-        if !root.join(&path).exists() {
-            remove.push(path);
+        let exists = match archive::split(&path) {
+            Some((arch, inner)) => listings
+                .entry(arch)
+                .or_insert_with_key(|arch| {
+                    archive::list(&root.join(arch))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect()
+                })
+                .contains(&inner),
+            // Archives are replaced by their contents when browsing them
+            None => root.join(&path).exists() && !(browse_archives && archive::is_archive(&path)),
+        };
+        if !exists {
+            remove.push((root_id, path));
         }
     }
-    Ok(FolderChanges { add, remove })
+    Ok(())
+}
+
+/// Replaces the `from` prefix of entry paths with `to`, for when a subfolder was moved
+pub struct PrefixRewrite {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl PrefixRewrite {
+    /// The rewritten path, if `path` starts with the prefix
+    pub fn apply(&self, path: &Path) -> Option<PathBuf> {
+        let rest = path.strip_prefix(&self.from).ok()?;
+        Some(self.to.join(rest))
+    }
+}
+
+/// How many of a sample of entries were found under a root
+#[derive(Default)]
+pub struct SampleCheck {
+    pub checked: usize,
+    pub found: usize,
+    /// Paths of the entries that weren't found
+    pub missing: Vec<PathBuf>,
+}
+
+impl SampleCheck {
+    /// Number of entries checked
+    const SIZE: usize = 50;
 }
 
 #[derive(Debug, Error)]
@@ -348,19 +972,23 @@ fn slice_contains_any_of<T: PartialEq>(haystack: &[T], needles: &[T]) -> bool {
 
 fn cleanse_tag_from_entries(entries: &mut Entries, tag_to_cleanse: tag::Id) {
     for en in entries.values_mut() {
-        en.tags.retain(|&tag| tag != tag_to_cleanse);
+        en.remove_tag(tag_to_cleanse);
     }
 }
 
-/// Rename the last component (filename) of a `PathBuf`, and rename it on the filesystem too.
-fn pathbuf_rename_filename(buf: &mut PathBuf, new_name: &str) -> anyhow::Result<()> {
+/// Rename the last component (filename) of a `PathBuf` relative to `root`,
+/// and rename it on the filesystem too.
+fn pathbuf_rename_filename(root: &Path, buf: &mut PathBuf, new_name: &str) -> anyhow::Result<()> {
+    if archive::is_virtual(buf) {
+        bail!("Files inside archives can't be renamed");
+    }
     let mut new_buf = buf.clone();
     new_buf.pop();
     new_buf.push(new_name);
-    if new_buf.exists() {
+    if root.join(&new_buf).exists() {
         bail!("Destination file already exists");
     }
-    std::fs::rename(&*buf, &new_buf)?;
+    std::fs::rename(root.join(&*buf), root.join(&new_buf))?;
     *buf = new_buf;
     Ok(())
 }
@@ -370,6 +998,10 @@ pub enum SortBy {
     Path,
     Id,
     NTags,
+    /// By rating, with favorites first among the same rating
+    Rating,
+    /// By the value of a tag. Entries without a value come last.
+    TagValue(tag::Id),
 }
 
 #[derive(PartialEq, Serialize, Deserialize, Clone, Copy)]
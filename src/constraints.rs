@@ -0,0 +1,116 @@
+use {
+    crate::{
+        collection::{Collection, Tags, TagsExt},
+        db::TagSet,
+        entry::{self, Entry},
+        tag,
+        tag_stats::implied_closure,
+    },
+    serde_derive::{Deserialize, Serialize},
+};
+
+/// A constraint that the tags of entries should satisfy.
+///
+/// Tags are checked with implications taken into account, the same way filters check them.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// An entry can't have both of these tags
+    Excludes(tag::Id, tag::Id),
+    /// An entry with this tag has to be part of a sequence
+    RequiresSeq(tag::Id),
+}
+
+pub type Rules = Vec<Rule>;
+
+impl Rule {
+    fn violated(self, id: entry::Id, have: &TagSet, coll: &Collection) -> bool {
+        match self {
+            Self::Excludes(a, b) => have.contains(&a) && have.contains(&b),
+            Self::RequiresSeq(tag) => {
+                have.contains(&tag) && !coll.sequences.values().any(|seq| seq.contains_entry(id))
+            }
+        }
+    }
+    pub fn describe(self, tags: &Tags) -> String {
+        match self {
+            Self::Excludes(a, b) => format!(
+                "{} excludes {}",
+                tags.first_name_of(&a),
+                tags.first_name_of(&b)
+            ),
+            Self::RequiresSeq(tag) => {
+                format!("{} requires a sequence", tags.first_name_of(&tag))
+            }
+        }
+    }
+    pub(crate) fn refers_to(self, tag: tag::Id) -> bool {
+        match self {
+            Self::Excludes(a, b) => a == tag || b == tag,
+            Self::RequiresSeq(rule_tag) => rule_tag == tag,
+        }
+    }
+    /// If the rule refers to `replace`, make it refer to `with` instead
+    pub(crate) fn replace_tag(&mut self, replace: tag::Id, with: tag::Id) {
+        let swap = |tag: &mut tag::Id| {
+            if *tag == replace {
+                *tag = with;
+            }
+        };
+        match self {
+            Self::Excludes(a, b) => {
+                swap(a);
+                swap(b);
+            }
+            Self::RequiresSeq(tag) => swap(tag),
+        }
+    }
+    /// Whether the rule can't mean anything, like a tag excluding itself
+    pub(crate) fn is_degenerate(self) -> bool {
+        matches!(self, Self::Excludes(a, b) if a == b)
+    }
+}
+
+/// The rules that entry `id` violates
+pub fn violated_rules(id: entry::Id, en: &Entry, coll: &Collection) -> impl Iterator<Item = Rule> {
+    let have = implied_closure(en.tags.iter().copied(), &coll.tags);
+    coll.rules
+        .iter()
+        .copied()
+        .filter(move |rule| rule.violated(id, &have, coll))
+}
+
+/// Every rule violation in the collection
+pub fn violations(coll: &Collection) -> Vec<(entry::Id, Rule)> {
+    let mut out = Vec::new();
+    if coll.rules.is_empty() {
+        return out;
+    }
+    for (id, en) in &coll.entries {
+        out.extend(violated_rules(*id, en, coll).map(|rule| (*id, rule)));
+    }
+    out
+}
+
+/// Rules that adding `new_tags` to `ids` would break, which aren't broken already
+pub fn broken_by_adding(coll: &Collection, ids: &[entry::Id], new_tags: &[tag::Id]) -> Vec<Rule> {
+    let mut broken = Vec::new();
+    if coll.rules.is_empty() {
+        return broken;
+    }
+    for id in ids {
+        let Some(en) = coll.entries.get(id) else {
+            continue;
+        };
+        let before = implied_closure(en.tags.iter().copied(), &coll.tags);
+        let after = implied_closure(en.tags.iter().chain(new_tags).copied(), &coll.tags);
+        for rule in &coll.rules {
+            if rule.violated(*id, &after, coll)
+                && !rule.violated(*id, &before, coll)
+                && !broken.contains(rule)
+            {
+                broken.push(*rule);
+            }
+        }
+    }
+    broken
+}
@@ -1,6 +1,6 @@
 pub mod global;
 use {
-    crate::{collection, entry, preferences::Preferences, serialization, tag},
+    crate::{collection, entry, preferences::Preferences, root, serialization, tag},
     anyhow::Context,
     directories::ProjectDirs,
     fnv::{FnvHashMap, FnvHashSet},
@@ -112,13 +112,16 @@ impl Db {
 #[derive(Debug, Default)]
 #[must_use]
 pub(crate) struct FolderChanges {
-    pub(crate) add: Vec<PathBuf>,
-    pub(crate) remove: Vec<PathBuf>,
+    pub(crate) add: Vec<(Option<root::Id>, PathBuf)>,
+    pub(crate) remove: Vec<(Option<root::Id>, PathBuf)>,
+    /// Roots that couldn't be scanned, like folders on an unmounted drive.
+    /// Their entries are left alone.
+    pub(crate) unavailable: Vec<Option<root::Id>>,
 }
 
 const FILENAME: &str = "cowbump.db";
 impl FolderChanges {
     pub(crate) fn empty(&self) -> bool {
-        self.add.is_empty() && self.remove.is_empty()
+        self.add.is_empty() && self.remove.is_empty() && self.unavailable.is_empty()
     }
 }
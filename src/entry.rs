@@ -1,22 +1,44 @@
 use {
     crate::{
-        collection::{Sequences, Tags, TagsExt},
+        collection::{Collection, Tags, TagsExt},
+        constraints,
         db::{TagSet, Uid},
         dlog,
         filter_reqs::{Req, Requirements},
-        gui::SelectionBufs,
-        tag,
+        notes, root, tag,
     },
+    fnv::FnvHashMap,
     serde_derive::{Deserialize, Serialize},
-    std::path::PathBuf,
+    std::{collections::BTreeMap, path::PathBuf},
 };
 
+/// Numeric values attached to tags of an entry, like `year=2019`
+pub type TagValues = FnvHashMap<tag::Id, f64>;
+/// String properties of an entry, keyed by names from [`Collection::prop_schema`]
+pub type Props = BTreeMap<String, String>;
+
 /// Path to an item we're interested in organizing, along with associated tags
 #[derive(Serialize, Deserialize)]
 pub struct Entry {
     /// Image path relative to collection root. Assumed to be unique within the collection.
     pub path: PathBuf,
     pub tags: TagSet,
+    /// Values of some of the tags in `tags`
+    #[serde(default)]
+    pub tag_values: TagValues,
+    /// Star rating from 1 to 5. 0 means not rated.
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub favorite: bool,
+    /// Free-form text, like source URLs or credits
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub props: Props,
+    /// Root that `path` is relative to. `None` is the primary root of the collection.
+    #[serde(default)]
+    pub root: Option<root::Id>,
 }
 
 #[derive(Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug)]
@@ -27,47 +49,97 @@ impl Entry {
         Self {
             path,
             tags: Default::default(),
+            tag_values: Default::default(),
+            rating: 0,
+            favorite: false,
+            notes: String::new(),
+            props: Props::new(),
+            root: None,
         }
     }
+    /// Whether the entry satisfies all of `reqs`.
+    ///
+    /// `inherited` are the tags the entry inherits from its sequences,
+    /// see [`Collection::inherited_tag_map`].
     pub fn all_reqs_satisfied(
         &self,
         id: Id,
         reqs: &Requirements,
-        tags: &Tags,
-        sequences: &Sequences,
-        sel_bufs: &SelectionBufs,
+        coll: &Collection,
+        inherited: &[tag::Id],
     ) -> bool {
-        reqs.all(|req| self.req_satisfied(id, req, tags, sequences, sel_bufs))
+        reqs.all(|req| self.req_satisfied(id, req, coll, inherited))
     }
     pub fn req_satisfied(
         &self,
         id: Id,
         req: &Req,
-        tags: &Tags,
-        sequences: &Sequences,
-        sel_bufs: &SelectionBufs,
+        coll: &Collection,
+        inherited: &[tag::Id],
     ) -> bool {
         match req {
-            Req::Any(reqs) => {
-                reqs.any(|req| self.req_satisfied(id, req, tags, sequences, sel_bufs))
-            }
-            Req::All(reqs) => {
-                reqs.all(|req| self.req_satisfied(id, req, tags, sequences, sel_bufs))
-            }
-            Req::None(reqs) => {
-                reqs.none(|req| self.req_satisfied(id, req, tags, sequences, sel_bufs))
+            Req::Any(reqs) => reqs.any(|req| self.req_satisfied(id, req, coll, inherited)),
+            Req::All(reqs) => reqs.all(|req| self.req_satisfied(id, req, coll, inherited)),
+            Req::None(reqs) => reqs.none(|req| self.req_satisfied(id, req, coll, inherited)),
+            Req::Tag(tag) => {
+                self.satisfies_required_tag(*tag, &coll.tags)
+                    || inherited.iter().any(|inherited| {
+                        tag_satisfies_required_tag(*inherited, *tag, &coll.tags, &mut 0)
+                    })
             }
-            Req::Tag(id) => self.satisfies_required_tag(*id, tags),
             Req::TagExact(id) => self.tags.iter().any(|tagid| tagid == id),
-            Req::Not(req) => !self.req_satisfied(id, req, tags, sequences, sel_bufs),
+            Req::Not(req) => !self.req_satisfied(id, req, coll, inherited),
             Req::FilenameSub(fsub) => self.path.to_string_lossy().to_lowercase().contains(fsub),
-            Req::PartOfSeq => sequences.values().any(|seq| seq.contains_entry(id)),
-            Req::PartOfSelectionGroup(name) => {
-                sel_bufs.any(|buf| buf.name.eq_ignore_ascii_case(name) && buf.buf.contains(&id))
-            }
+            Req::PartOfSeq => coll.sequences.values().any(|seq| seq.contains_entry(id)),
+            Req::PartOfNamedSeq(name) => coll
+                .sequences
+                .values()
+                .any(|seq| seq.name_matches(name) && seq.contains_entry(id)),
+            Req::SeqFirst => coll
+                .sequences
+                .values()
+                .any(|seq| seq.entries.first() == Some(&id)),
+            Req::SeqLast => coll
+                .sequences
+                .values()
+                .any(|seq| seq.entries.last() == Some(&id)),
+            Req::SeqUnfinished => coll
+                .sequences
+                .values()
+                .any(|seq| seq.is_unfinished() && seq.contains_entry(id)),
+            Req::SeqLen(cmp) => coll
+                .sequences
+                .values()
+                .any(|seq| seq.contains_entry(id) && cmp.matches(seq.entries.len())),
+            Req::PartOfSelectionGroup(name) => coll
+                .sel_bufs
+                .any(|buf| buf.name.eq_ignore_ascii_case(name) && buf.buf.contains(&id)),
             Req::NTags(n) => self.tags.len() == *n,
+            Req::TagValue(tag, cmp) => self
+                .tag_values
+                .get(tag)
+                .is_some_and(|val| cmp.matches(*val)),
+            Req::Rating(cmp) => cmp.matches(self.rating),
+            Req::Favorite => self.favorite,
+            Req::Prop { key, value } => self
+                .prop_ignore_case(key)
+                .is_some_and(|val| val.to_lowercase() == *value),
+            Req::HasProp(key) => self
+                .prop_ignore_case(key)
+                .is_some_and(|val| !val.is_empty()),
+            Req::Related(kind) => coll
+                .relations_of(id)
+                .any(|rel| kind.is_none_or(|kind| rel.kind == kind)),
+            Req::Note(query) => notes::notes_contain(&self.notes, query),
+            Req::Violations => constraints::violated_rules(id, self, coll).next().is_some(),
         }
     }
+    /// Filters are lowercase, so property names are matched ignoring case
+    fn prop_ignore_case(&self, key: &str) -> Option<&str> {
+        self.props
+            .iter()
+            .find_map(|(k, v)| k.eq_ignore_ascii_case(key).then_some(v.as_str()))
+    }
     fn satisfies_required_tag(&self, required_tag_id: tag::Id, tags: &Tags) -> bool {
         self.tags
             .iter()
@@ -77,8 +149,16 @@ impl Entry {
     pub(crate) fn replace_tag(&mut self, replace: tag::Id, with: tag::Id) {
         if self.tags.remove(&replace) {
             self.tags.insert(with);
+            if let Some(val) = self.tag_values.remove(&replace) {
+                self.tag_values.entry(with).or_insert(val);
+            }
         }
     }
+    /// Remove a tag, along with its value. Returns whether the entry had the tag.
+    pub(crate) fn remove_tag(&mut self, tag: tag::Id) -> bool {
+        self.tag_values.remove(&tag);
+        self.tags.remove(&tag)
+    }
 }
 
 fn tag_satisfies_required_tag(
@@ -113,11 +193,10 @@ pub fn filter_map(
     uid: Id,
     entry: &Entry,
     reqs: &Requirements,
-    tags: &Tags,
-    sequences: &Sequences,
-    sel_bufs: &SelectionBufs,
+    coll: &Collection,
+    inherited: &[tag::Id],
 ) -> Option<Id> {
-    if entry.all_reqs_satisfied(uid, reqs, tags, sequences, sel_bufs) {
+    if entry.all_reqs_satisfied(uid, reqs, coll, inherited) {
         Some(uid)
     } else {
         None
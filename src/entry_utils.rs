@@ -1,6 +1,9 @@
 //! Misc entry/collection utilities
 
-use crate::{collection::Collection, db::TagSet, entry};
+use {
+    crate::{collection::Collection, db::TagSet, entry, sequence, tag},
+    std::{cmp::Ordering, iter::Peekable, path::Path, str::Chars},
+};
 
 pub fn common_tags(ids: &[entry::Id], coll: &Collection) -> TagSet {
     let mut set = TagSet::default();
@@ -14,3 +17,61 @@ pub fn common_tags(ids: &[entry::Id], coll: &Collection) -> TagSet {
     }
     set
 }
+
+/// Tags that any of `ids` inherit from a sequence, along with the first such sequence
+pub fn inherited_tags(ids: &[entry::Id], coll: &Collection) -> Vec<(tag::Id, sequence::Id)> {
+    let mut tags: Vec<(tag::Id, sequence::Id)> = Vec::new();
+    for &id in ids {
+        for (tag, seq) in coll.inherited_tags(id) {
+            if !tags.iter().any(|(t, _)| *t == tag) {
+                tags.push((tag, seq));
+            }
+        }
+    }
+    tags
+}
+
+/// Compare strings so that embedded numbers are ordered by value, e.g. `page2` comes before `page10`.
+///
+/// Non-numeric parts are compared case insensitively.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        let ord = match (a_chars.peek(), b_chars.peek()) {
+            // Equal apart from case or leading zeroes. Fall back to plain comparison for stability.
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let a_num = take_digits(&mut a_chars);
+                let b_num = take_digits(&mut b_chars);
+                let a_num = a_num.trim_start_matches('0');
+                let b_num = b_num.trim_start_matches('0');
+                a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num))
+            }
+            (Some(ca), Some(cb)) => {
+                let ord = ca.to_lowercase().cmp(cb.to_lowercase());
+                a_chars.next();
+                b_chars.next();
+                ord
+            }
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// [`natural_cmp`] for paths
+pub fn natural_path_cmp(a: &Path, b: &Path) -> Ordering {
+    natural_cmp(&a.to_string_lossy(), &b.to_string_lossy())
+}
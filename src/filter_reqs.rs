@@ -1,9 +1,9 @@
 use {
     crate::{
         collection::{Collection, Tags, TagsExt},
-        tag,
+        relation, tag,
     },
-    std::borrow::Cow,
+    std::{borrow::Cow, fmt::Display, str::FromStr},
     tagfilter_lang::Requirement,
     thiserror::Error,
 };
@@ -117,6 +117,13 @@ impl Requirements {
         }
         buf
     }
+    /// Texts of the top level note searches
+    pub(crate) fn note_queries(&self) -> impl Iterator<Item = &str> {
+        self.reqs.iter().filter_map(|req| match req {
+            Req::Note(query) => Some(query.as_str()),
+            _ => None,
+        })
+    }
     pub fn is_empty(&self) -> bool {
         self.reqs.is_empty()
     }
@@ -157,8 +164,86 @@ pub enum Req {
     Not(Box<Req>),
     FilenameSub(String),
     PartOfSeq,
+    PartOfNamedSeq(String),
+    SeqFirst,
+    SeqLast,
+    /// Part of a sequence that was started, but not read to the end
+    SeqUnfinished,
+    SeqLen(NumCmp<usize>),
     PartOfSelectionGroup(String),
     NTags(usize),
+    /// Has a value for the tag, which matches the comparison
+    TagValue(tag::Id, NumCmp<f64>),
+    Rating(NumCmp<u8>),
+    Favorite,
+    /// The entry has property `key` equal to `value` (lowercase)
+    Prop {
+        key: String,
+        value: String,
+    },
+    /// The entry has a non-empty value for this property
+    HasProp(String),
+    /// Has a relation of this kind (either way), or any relation if `None`
+    Related(Option<relation::Kind>),
+    /// The notes of the entry contain this text (lowercase)
+    Note(String),
+    /// Breaks at least one of the collection's rules
+    Violations,
+}
+
+/// A numeric comparison, like `>20`, `<=5`, `=3`, `3`, or the inclusive range `3..10`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NumCmp<T> {
+    Eq(T),
+    Lt(T),
+    Le(T),
+    Gt(T),
+    Ge(T),
+    Range(T, T),
+}
+
+impl<T: PartialOrd + FromStr + Copy> NumCmp<T> {
+    pub fn parse(src: &str) -> Option<Self> {
+        if let Some((lo, hi)) = src.strip_prefix('=').unwrap_or(src).split_once("..") {
+            return Some(Self::Range(lo.parse().ok()?, hi.parse().ok()?));
+        }
+        // Two character operators need to be tried before their one character prefixes
+        let cmp = if let Some(rhs) = src.strip_prefix(">=") {
+            Self::Ge(rhs.parse().ok()?)
+        } else if let Some(rhs) = src.strip_prefix("<=") {
+            Self::Le(rhs.parse().ok()?)
+        } else if let Some(rhs) = src.strip_prefix('>') {
+            Self::Gt(rhs.parse().ok()?)
+        } else if let Some(rhs) = src.strip_prefix('<') {
+            Self::Lt(rhs.parse().ok()?)
+        } else {
+            Self::Eq(src.strip_prefix('=').unwrap_or(src).parse().ok()?)
+        };
+        Some(cmp)
+    }
+    pub fn matches(&self, val: T) -> bool {
+        match *self {
+            Self::Eq(rhs) => val == rhs,
+            Self::Lt(rhs) => val < rhs,
+            Self::Le(rhs) => val <= rhs,
+            Self::Gt(rhs) => val > rhs,
+            Self::Ge(rhs) => val >= rhs,
+            Self::Range(lo, hi) => val >= lo && val <= hi,
+        }
+    }
+}
+
+impl<T: Display> Display for NumCmp<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Eq(rhs) => write!(f, "{rhs}"),
+            Self::Lt(rhs) => write!(f, "<{rhs}"),
+            Self::Le(rhs) => write!(f, "<={rhs}"),
+            Self::Gt(rhs) => write!(f, ">{rhs}"),
+            Self::Ge(rhs) => write!(f, ">={rhs}"),
+            Self::Range(lo, hi) => write!(f, "{lo}..{hi}"),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -179,12 +264,18 @@ impl Req {
         coll: &Collection,
     ) -> Result<Self, ReqTransformError<'src>> {
         let req = match tf_req {
-            Requirement::Tag(name) => {
-                let id = coll
-                    .resolve_tag(name)
-                    .ok_or(ReqTransformError::NoSuchTag(name))?;
-                Req::Tag(id)
-            }
+            Requirement::Tag(name) => match coll.resolve_tag(name) {
+                Some(id) => Req::Tag(id),
+                None => {
+                    let (tag_name, cmp) =
+                        split_value_cmp(name).ok_or(ReqTransformError::NoSuchTag(name))?;
+                    let id = coll
+                        .resolve_tag(tag_name)
+                        .ok_or(ReqTransformError::NoSuchTag(tag_name))?;
+                    let cmp = NumCmp::parse(cmp).ok_or(ReqTransformError::InvalidParameter)?;
+                    Req::TagValue(id, cmp)
+                }
+            },
             Requirement::TagExact(name) => {
                 let id = coll
                     .resolve_tag(name)
@@ -219,7 +310,18 @@ impl Req {
                     };
                     Req::FilenameSub((*filename_sub).to_owned())
                 }
-                "seq" | "sequence" => Req::PartOfSeq,
+                "seq" | "sequence" => match call.params.first() {
+                    Some(_) => Req::PartOfNamedSeq(word_param(&call.params)?.to_owned()),
+                    None => Req::PartOfSeq,
+                },
+                "seq-first" => Req::SeqFirst,
+                "seq-last" => Req::SeqLast,
+                "seq-unfinished" => Req::SeqUnfinished,
+                "seqlen" => {
+                    let cmp = NumCmp::parse(word_param(&call.params)?)
+                        .ok_or(ReqTransformError::InvalidParameter)?;
+                    Req::SeqLen(cmp)
+                }
                 "sel" => {
                     let group_name = match call.params.first() {
                         Some(req) => match req {
@@ -233,6 +335,33 @@ impl Req {
                     Req::PartOfSelectionGroup((*group_name).to_owned())
                 }
                 "notag" | "no-tag" | "untagged" => Req::NTags(0),
+                "violations" | "violation" => Req::Violations,
+                "rating" => {
+                    let cmp = NumCmp::parse(word_param(&call.params)?)
+                        .ok_or(ReqTransformError::InvalidParameter)?;
+                    Req::Rating(cmp)
+                }
+                "fav" | "favorite" => Req::Favorite,
+                "prop" => {
+                    let (key, value) = word_param(&call.params)?
+                        .split_once('=')
+                        .ok_or(ReqTransformError::InvalidParameter)?;
+                    Req::Prop {
+                        key: key.to_owned(),
+                        value: value.replace('_', " ").to_lowercase(),
+                    }
+                }
+                "related" | "rel" => match call.params.first() {
+                    Some(_) => Req::Related(Some(
+                        relation::Kind::from_filter_word(word_param(&call.params)?)
+                            .ok_or(ReqTransformError::InvalidParameter)?,
+                    )),
+                    None => Req::Related(None),
+                },
+                "has" => Req::HasProp(word_param(&call.params)?.to_owned()),
+                "note" | "notes" => {
+                    Req::Note(word_param(&call.params)?.replace('_', " ").to_lowercase())
+                }
                 "ntags" => match call.params.first() {
                     Some(Requirement::Tag(tag) | Requirement::TagExact(tag)) => {
                         match tag.parse::<usize>() {
@@ -260,13 +389,49 @@ impl Req {
             Req::Not(req) => format!("!{}", req.to_string(tags)).into(),
             Req::FilenameSub(substr) => format!("@f[{substr}]").into(),
             Req::PartOfSeq => "@seq".into(),
+            Req::PartOfNamedSeq(name) => format!("@seq[{name}]").into(),
+            Req::SeqFirst => "@seq-first".into(),
+            Req::SeqLast => "@seq-last".into(),
+            Req::SeqUnfinished => "@seq-unfinished".into(),
+            Req::SeqLen(cmp) => format!("@seqlen[{cmp}]").into(),
             Req::PartOfSelectionGroup(name) => format!("@sel[{name}]").into(),
             Req::NTags(0) => "@untagged".into(),
             Req::NTags(n) => format!("@ntags[{n}]").into(),
+            Req::TagValue(id, cmp @ (NumCmp::Eq(_) | NumCmp::Range(..))) => {
+                format!("{}={cmp}", tags.first_name_of(id)).into()
+            }
+            Req::TagValue(id, cmp) => format!("{}{cmp}", tags.first_name_of(id)).into(),
+            Req::Rating(cmp) => format!("@rating[{cmp}]").into(),
+            Req::Favorite => "@fav".into(),
+            Req::Prop { key, value } => format!("@prop[{key}={}]", value.replace(' ', "_")).into(),
+            Req::HasProp(key) => format!("@has[{key}]").into(),
+            Req::Related(None) => "@related".into(),
+            Req::Related(Some(kind)) => format!("@related[{}]", kind.filter_word()).into(),
+            Req::Note(query) => format!("@note[{}]", query.replace(' ', "_")).into(),
+            Req::Violations => "@violations".into(),
         }
     }
 }
 
+/// Split a tag value comparison like `year>=2015`, `score=3..5` or `score[3..5]`
+/// into the tag name and the comparison
+pub(crate) fn split_value_cmp(word: &str) -> Option<(&str, &str)> {
+    if let Some(inner) = word.strip_suffix(']') {
+        return inner.split_once('[');
+    }
+    let pos = word.find(['<', '>', '='])?;
+    Some(word.split_at(pos))
+}
+
+/// Extract a single word parameter from a function call's parameter list
+fn word_param<'src>(params: &[Requirement<'src>]) -> Result<&'src str, ReqTransformError<'src>> {
+    match params.first() {
+        Some(Requirement::Tag(word) | Requirement::TagExact(word)) => Ok(*word),
+        Some(_) => Err(ReqTransformError::InvalidParameter),
+        None => Err(ReqTransformError::MissingParameter),
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ParseResolveError<'a> {
     #[error("{0}")]
@@ -2,6 +2,7 @@ pub mod debug_log;
 mod egui_ui;
 mod open;
 mod resources;
+mod tagger;
 mod thumbnail_loader;
 mod thumbnails_view;
 mod viewer;
@@ -10,18 +11,20 @@ use {
     self::{
         egui_ui::{Action, EguiState},
         resources::Resources,
+        tagger::TaggerState,
         thumbnail_loader::ThumbnailLoader,
         thumbnails_view::{ThumbnailsView, handle_event, search_next, search_prev, select_all},
         viewer::ViewerState,
     },
     crate::{
         application::Application,
-        collection::{self, Entries},
+        collection::{self, Collection},
         db::EntryMap,
         entry,
         filter_reqs::Requirements,
         gui::thumbnails_view::add_all_to_selection,
         preferences::{LightDarkPref, Preferences, SortPreference},
+        tag_stats::CoOccurrence,
     },
     anyhow::Context as _,
     arboard::Clipboard,
@@ -79,7 +82,6 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
                     &coll.1,
                     &state.filter,
                     &app.database.preferences,
-                    &state.sel,
                 );
                 let root_path = &app.database.collections[&coll.0];
                 if let Err(e) = std::env::set_current_dir(root_path) {
@@ -97,7 +99,8 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
     }
 
     while window.is_open() {
-        if !sf_egui.context().egui_wants_keyboard_input() {
+        // Plain arrow keys move the thumbnail cursor, so continuous scrolling needs ctrl
+        if !sf_egui.context().egui_wants_keyboard_input() && Key::LControl.is_pressed() {
             let scroll_speed = app.database.preferences.arrow_key_scroll_speed;
             if Key::Down.is_pressed() {
                 state.thumbs_view.y_offset += scroll_speed;
@@ -151,8 +154,17 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
                     }
                 }
                 Activity::Viewer => {
-                    if egui_state.ptr_over_content_area {
-                        viewer::handle_event(&mut state, &event, &window);
+                    if egui_state.ptr_over_content_area
+                        && let Some((_id, coll)) = &mut app.active_collection
+                    {
+                        viewer::handle_event(&mut state, &event, coll, &window);
+                    }
+                }
+                Activity::Tagging => {
+                    if !sf_egui.context().egui_wants_keyboard_input()
+                        && let Some((_id, coll)) = &mut app.active_collection
+                    {
+                        tagger::handle_event(&mut state, &event, coll, &window);
                     }
                 }
             }
@@ -174,7 +186,11 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
                     app.no_save = true;
                     window.close();
                 }
-                Action::SelectNone => state.sel.clear_current(),
+                Action::SelectNone => {
+                    if let Some((_, coll)) = &mut app.active_collection {
+                        coll.sel_bufs.clear_current();
+                    }
+                }
                 Action::FindNext => {
                     if let Some((_, coll)) = &mut app.active_collection {
                         search_next(&mut state, coll, window.size().y);
@@ -187,30 +203,51 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
                 }
                 Action::SelectAll => {
                     if let Some((_, coll)) = &mut app.active_collection {
-                        select_all(&mut state, coll);
+                        select_all(&state, coll);
                     }
                 }
                 Action::AddAllToSelection => {
                     if let Some((_, coll)) = &mut app.active_collection {
-                        add_all_to_selection(&mut state, coll);
+                        add_all_to_selection(&state, coll);
                     }
                 }
                 Action::Sort => {
                     if let Some((_, coll)) = &mut app.active_collection {
                         state
                             .thumbs_view
-                            .update_from_collection(coll, &state.filter, &state.sel);
+                            .update_from_collection(coll, &state.filter);
                     }
                 }
                 Action::Shuffle => {
                     state.thumbs_view.uids.shuffle(&mut rand::rng());
                 }
                 Action::OpenEntriesWindow => {
-                    let id_vec = state
-                        .sel
-                        .current_as_nonempty_id_vec()
-                        .context("Selection buffer inaccessible")?;
-                    egui_state.add_entries_window(id_vec.clone());
+                    if let Some((_, coll)) = &app.active_collection {
+                        let id_vec = coll
+                            .sel_bufs
+                            .current_as_nonempty_id_vec()
+                            .context("Selection buffer inaccessible")?;
+                        egui_state.add_entries_window(id_vec.clone());
+                    }
+                }
+                Action::SelectionOp(op) => {
+                    if let Some((_, coll)) = &mut app.active_collection {
+                        let filtered: Vec<_> = coll.filter(&state.filter).collect();
+                        if let Err(e) = coll.sel_bufs.apply(*op, filtered) {
+                            egui_state
+                                .modal
+                                .err(format!("Selection operation failed: {e:?}"));
+                        }
+                    }
+                }
+                Action::StartTagging => {
+                    if let Some((_, coll)) = &app.active_collection
+                        && let Err(e) = tagger::start(&mut state, coll, &window)
+                    {
+                        egui_state
+                            .modal
+                            .err(format!("Couldn't start rapid tagging: {e}"));
+                    }
                 }
             }
         }
@@ -222,8 +259,9 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
             && !sf_egui.context().egui_wants_keyboard_input()
             && egui_state.ptr_over_content_area
             && !egui_state.just_closed_window_with_esc
+            && let Some((_, coll)) = &mut app.active_collection
         {
-            state.sel.clear_current();
+            coll.sel_bufs.clear_current();
         }
         window.clear(Color::BLACK);
         let painter = egui::Painter::new(
@@ -235,11 +273,12 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
         if let Some((_, coll)) = &mut app.active_collection {
             match state.activity {
                 Activity::Thumbnails => {
+                    state.thumbs_view.update_drag(&window, &mut coll.sel_bufs);
                     thumbnails_view::draw_thumbnails(
                         &mut state,
                         &res,
                         &mut window,
-                        &coll.entries,
+                        coll,
                         load_anim_rotation,
                         egui_state.ptr_over_content_area,
                         &painter,
@@ -247,8 +286,12 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
                 }
                 Activity::Viewer => {
                     viewer::update(&mut state, &window);
+                    viewer::record_progress(&state.viewer_state, coll);
                     viewer::draw(&mut state, &mut window, coll, &painter);
                 }
+                Activity::Tagging => {
+                    tagger::draw(&mut state, &mut window, coll, &painter);
+                }
             }
             egui_state.loading_changes_notify = false;
             if let Some(recv) = &app.folder_changes_recv {
@@ -272,7 +315,9 @@ pub fn run(app: &mut Application) -> anyhow::Result<()> {
                 }
             }
         }
-        if let Some(index) = state.thumbs_view.highlight {
+        if let Some(index) = state.thumbs_view.highlight
+            && state.activity == Activity::Thumbnails
+        {
             let mut search_highlight = RectangleShape::with_size(
                 (
                     state.thumbs_view.thumb_size as f32,
@@ -332,106 +377,20 @@ struct State {
     search_success: bool,
     clipboard_ctx: Clipboard,
     thumbs_view: ThumbnailsView,
-    sel: SelectionBufs,
     /// For batch select, this marks the "a" point
     select_a: Option<usize>,
     activity: Activity,
     viewer_state: ViewerState,
+    tagger: TaggerState,
+    /// Tag co-occurrence statistics, for suggesting tags
+    tag_stats: CoOccurrence,
 }
-pub struct SelectionBuf {
-    pub buf: Vec<entry::Id>,
-    pub name: String,
-}
-
-impl SelectionBuf {
-    pub fn new(name: impl Into<String>) -> Self {
-        Self {
-            buf: Vec::new(),
-            name: name.into(),
-        }
-    }
-    pub fn clear(&mut self) {
-        self.buf.clear();
-    }
-    pub fn extend(&mut self, iter: impl IntoIterator<Item = entry::Id>) {
-        self.buf.extend(iter);
-    }
-    pub fn as_vec(&self) -> &Vec<entry::Id> {
-        &self.buf
-    }
-    pub fn remove(&mut self, idx: usize) {
-        self.buf.remove(idx);
-    }
-    pub fn len(&self) -> usize {
-        self.buf.len()
-    }
-    pub fn contains(&self, id: &entry::Id) -> bool {
-        self.buf.contains(id)
-    }
-}
-
-pub struct SelectionBufs {
-    current: usize,
-    bufs: Vec<SelectionBuf>,
-}
-
-impl SelectionBufs {
-    pub fn new() -> Self {
-        Self {
-            current: 0,
-            bufs: vec![SelectionBuf::new("sel-1")],
-        }
-    }
-    pub fn current(&self) -> Option<&SelectionBuf> {
-        self.bufs.get(self.current)
-    }
-    pub fn current_mut(&mut self) -> Option<&mut SelectionBuf> {
-        self.bufs.get_mut(self.current)
-    }
-    pub fn for_each_mut(&mut self, mut f: impl FnMut(&mut SelectionBuf)) {
-        for buf in &mut self.bufs {
-            f(buf);
-        }
-    }
-    pub fn any(&self, f: impl FnMut(&SelectionBuf) -> bool) -> bool {
-        self.bufs.iter().any(f)
-    }
-    pub fn add_buf(&mut self, name: impl Into<String>) {
-        self.bufs.push(SelectionBuf::new(name));
-    }
-    fn n_selected(&self) -> usize {
-        self.current().map_or(0, SelectionBuf::len)
-    }
-    fn none_selected(&self) -> bool {
-        self.n_selected() == 0
-    }
-    /// Returns the currently active selected-ids vec, if any, if not empty
-    fn current_as_nonempty_id_vec(&self) -> Option<&Vec<entry::Id>> {
-        self.current()
-            .filter(|buf| !buf.buf.is_empty())
-            .map(SelectionBuf::as_vec)
-    }
-    fn selected_ids_iter(&self) -> impl Iterator<Item = &entry::Id> {
-        match self.current() {
-            Some(buf) => buf.buf.iter(),
-            None => [].iter(),
-        }
-    }
-    fn current_contains(&self, id: &entry::Id) -> bool {
-        self.current().is_some_and(|buf| buf.contains(id))
-    }
-
-    fn clear_current(&mut self) {
-        if let Some(current) = self.current_mut() {
-            current.clear();
-        }
-    }
-}
-
 #[derive(PartialEq, Eq)]
 enum Activity {
     Thumbnails,
     Viewer,
+    /// Rapid tagging, one entry at a time
+    Tagging,
 }
 
 fn set_active_collection(
@@ -440,7 +399,6 @@ fn set_active_collection(
     id: collection::Id,
     reqs: &Requirements,
     window_width: u32,
-    sel_bufs: &SelectionBufs,
 ) -> anyhow::Result<()> {
     app.save_active_collection()?;
     let active_coll = &app
@@ -448,13 +406,8 @@ fn set_active_collection(
         .as_ref()
         .context("No active collection")?
         .1;
-    *entries_view = ThumbnailsView::from_collection(
-        window_width,
-        active_coll,
-        reqs,
-        &app.database.preferences,
-        sel_bufs,
-    );
+    *entries_view =
+        ThumbnailsView::from_collection(window_width, active_coll, reqs, &app.database.preferences);
     let root = app
         .database
         .collections
@@ -471,7 +424,7 @@ struct TexProperties {
 fn get_tex_for_entry<'t>(
     thumbnail_cache: &'t ThumbnailCache,
     id: entry::Id,
-    entries: &Entries,
+    coll: &Collection,
     thumbnail_loader: &ThumbnailLoader,
     thumb_size: u32,
     res: &'t Resources,
@@ -494,7 +447,7 @@ fn get_tex_for_entry<'t>(
             ),
         },
         None => {
-            let Some(entry) = entries.get(&id) else {
+            let Some(entry) = coll.entries.get(&id) else {
                 return (
                     TexProperties {
                         has_img: false,
@@ -503,7 +456,7 @@ fn get_tex_for_entry<'t>(
                     &*res.error_texture,
                 );
             };
-            thumbnail_loader.request(&entry.path, thumb_size, id);
+            thumbnail_loader.request(&coll.entry_path(entry), thumb_size, id);
             (
                 TexProperties {
                     has_img: false,
@@ -527,10 +480,11 @@ impl State {
             clipboard_ctx: Clipboard::new()?,
             thumbs_view: ThumbnailsView::new(window_width, prefs),
             find_reqs: Requirements::default(),
-            sel: SelectionBufs::new(),
             select_a: None,
             activity: Activity::Thumbnails,
             viewer_state: ViewerState::default(),
+            tagger: TaggerState::default(),
+            tag_stats: CoOccurrence::default(),
         })
     }
     fn wipe_search(&mut self) {
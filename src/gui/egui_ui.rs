@@ -7,13 +7,19 @@ mod entries_window;
 mod filter_popup;
 mod find_popup;
 mod icons;
+mod implied_tags_window;
+mod import_window;
 mod load_folder_window;
 mod modal;
 mod preferences_window;
 mod query_popup;
+mod rules_window;
+mod seq_detect_window;
 mod sequences;
 mod tag_autocomplete;
 mod tag_list;
+mod tag_stats_window;
+mod tag_tree;
 mod top_bar;
 mod ui_ext;
 
@@ -25,19 +31,28 @@ use {
         collections_window::CollectionsDbWindow,
         debug_window::DebugWindow,
         entries_window::EntriesWindow,
+        implied_tags_window::ImpliedTagsWindow,
+        import_window::ImportWindow,
         load_folder_window::LoadFolderWindow,
         preferences_window::PreferencesWindow,
         query_popup::QueryPopup,
+        rules_window::RulesWindow,
+        seq_detect_window::SeqDetectWindow,
         sequences::{SequenceWindow, SequencesWindow},
         tag_list::TagWindow,
+        tag_stats_window::TagStatsWindow,
     },
     super::{get_tex_for_entry, resources::Resources},
     crate::{
         application::Application,
+        cbz,
         collection::{Collection, TagsExt},
         entry,
-        gui::State,
+        gui::{Activity, State},
         preferences::Preferences,
+        root,
+        selection::SetOp,
+        tag,
     },
     egui_colors::Colorix,
     egui_file_dialog::FileDialog,
@@ -45,6 +60,7 @@ use {
         egui::{self, Context, FontFamily, FontId, TextStyle},
         sf2g::graphics::{RenderTarget, RenderWindow, Texture},
     },
+    fnv::FnvHashMap,
     modal::{ModalDialog, PromptAction},
     top_bar::TopBar,
 };
@@ -56,6 +72,10 @@ pub(crate) struct EguiState {
     sequence_windows: Vec<SequenceWindow>,
     pub preferences_window: PreferencesWindow,
     pub tag_window: TagWindow,
+    pub(crate) tag_stats_window: TagStatsWindow,
+    pub(crate) implied_tags_window: ImpliedTagsWindow,
+    pub(crate) rules_window: RulesWindow,
+    pub(crate) seq_detect_window: SeqDetectWindow,
     pub(crate) action: Option<Action>,
     pub load_folder_window: LoadFolderWindow,
     pub(crate) changes_window: ChangesWindow,
@@ -69,10 +89,13 @@ pub(crate) struct EguiState {
     pub(crate) coll_prefs_window: CollPrefsWindow,
     pub(crate) batch_rename_window: BatchRenameWindow,
     pub(crate) collections_db_window: CollectionsDbWindow,
+    pub(crate) import_window: ImportWindow,
     pub(crate) file_dialog: FileDialog,
     /// If `Some`, save this screenshot to the selected path of the file dialog
     pub(crate) file_op: Option<FileOp>,
     pub(crate) modal: ModalDialog,
+    /// Write a ComicInfo.xml into exported CBZ archives
+    pub(crate) cbz_comic_info: bool,
     pub(crate) colorix: Option<Colorix>,
     /// Whether to tell the user we're loading folder changes
     pub loading_changes_notify: bool,
@@ -84,6 +107,9 @@ pub(crate) enum FileOp {
     OpenDirectory,
     CreateBackup,
     RestoreBackup,
+    ExportCbz(cbz::Export),
+    ImportCbz,
+    AddRoot,
 }
 
 impl EguiState {
@@ -94,6 +120,10 @@ impl EguiState {
             sequence_windows: Default::default(),
             preferences_window: Default::default(),
             tag_window: Default::default(),
+            tag_stats_window: Default::default(),
+            implied_tags_window: Default::default(),
+            rules_window: Default::default(),
+            seq_detect_window: Default::default(),
             action: Default::default(),
             top_bar: Default::default(),
             load_folder_window: Default::default(),
@@ -106,10 +136,12 @@ impl EguiState {
             coll_prefs_window: Default::default(),
             batch_rename_window: Default::default(),
             collections_db_window: Default::default(),
+            import_window: Default::default(),
             file_dialog: FileDialog::new()
                 .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::default()),
             file_op: None,
             modal: ModalDialog::default(),
+            cbz_comic_info: true,
             colorix: prefs
                 .color_theme
                 .as_ref()
@@ -131,6 +163,8 @@ pub(crate) enum Action {
     OpenEntriesWindow,
     Shuffle,
     Sort,
+    SelectionOp(SetOp),
+    StartTagging,
 }
 
 impl EguiState {
@@ -175,6 +209,38 @@ pub(super) fn do_ui(
                     .modal
                     .success(format!("Successful merge into {into_name}"));
             }
+            PromptAction::MergeTags(ref pairs) => {
+                let Some((_, coll)) = &mut app.active_collection else {
+                    anyhow::bail!("No active collection");
+                };
+                // Where already merged tags went, so later pairs can follow them
+                let mut merged_into = FnvHashMap::default();
+                let follow = |merged_into: &FnvHashMap<tag::Id, tag::Id>, mut id| {
+                    while let Some(&into) = merged_into.get(&id) {
+                        id = into;
+                    }
+                    id
+                };
+                let mut n_merged = 0;
+                for &(merge, into) in pairs {
+                    let merge = follow(&merged_into, merge);
+                    let into = follow(&merged_into, into);
+                    if merge == into {
+                        continue;
+                    }
+                    coll.merge_tags(merge, into)?;
+                    merged_into.insert(merge, into);
+                    n_merged += 1;
+                }
+                egui_state.modal.success(format!("Merged {n_merged} tags"));
+            }
+            PromptAction::RemoveRoot(id) => {
+                let Some((_, coll)) = &mut app.active_collection else {
+                    anyhow::bail!("No active collection");
+                };
+                coll.remove_root(id);
+                egui_state.action = Some(Action::Sort);
+            }
             PromptAction::PanicTest => panic!("User inflicted panic"),
         }
     }
@@ -196,15 +262,20 @@ pub(super) fn do_ui(
     changes_window::do_frame(state, egui_state, ui, app, win);
     debug_window::do_frame(egui_state, ui);
     collections_window::do_frame(app, egui_state, ui);
+    import_window::do_frame(state, egui_state, app, ui);
     if let Some((_id, coll)) = app.active_collection.as_mut() {
         find_popup::do_frame(state, egui_state, ui, coll, win);
         if filter_popup::do_frame(state, egui_state, ui, coll) {
             state
                 .thumbs_view
-                .update_from_collection(coll, &state.filter, &state.sel);
+                .update_from_collection(coll, &state.filter);
             state.thumbs_view.clamp_bottom(win);
         }
         tag_list::do_frame(state, egui_state, coll, ui, &mut app.database.uid_counter);
+        tag_stats_window::do_frame(egui_state, coll, ui);
+        implied_tags_window::do_frame(state, egui_state, coll, ui);
+        rules_window::do_frame(state, egui_state, coll, ui);
+        seq_detect_window::do_frame(egui_state, coll, &mut app.database.uid_counter, ui);
         sequences::do_sequences_window(
             state,
             egui_state,
@@ -218,6 +289,7 @@ pub(super) fn do_ui(
             state,
             egui_state,
             coll,
+            &mut app.database.uid_counter,
             ui,
             &mut app.database.preferences,
             win,
@@ -225,6 +297,9 @@ pub(super) fn do_ui(
         coll_prefs_window::do_frame(egui_state, coll, ui, &app.database.preferences);
         entries_window::do_frame(state, egui_state, coll, ui, win, &mut app.database, res);
         batch_rename_window::do_frame(state, egui_state, coll, ui, win);
+        if state.activity == Activity::Tagging {
+            crate::gui::tagger::overlay_ui(ui, state, coll);
+        }
     }
     if let Some(op) = &egui_state.file_op
         && let Some(path) = egui_state.file_dialog.take_picked()
@@ -239,7 +314,6 @@ pub(super) fn do_ui(
                         id,
                         &state.filter,
                         win.size().x,
-                        &state.sel,
                     );
                     if let Err(e) = result {
                         egui_state
@@ -274,6 +348,62 @@ pub(super) fn do_ui(
                     egui_state.modal.success("Backup restored");
                 }
             }
+            FileOp::ExportCbz(export) => {
+                let path = if path.extension().is_none() {
+                    path.with_extension("cbz")
+                } else {
+                    path
+                };
+                if let Some((_, coll)) = &app.active_collection {
+                    match cbz::export(coll, export, &path) {
+                        Ok(()) => egui_state.modal.success(format!(
+                            "Exported {} entries to {}",
+                            export.ids.len(),
+                            path.display()
+                        )),
+                        Err(e) => egui_state.modal.err(format!("CBZ export failed: {e:?}")),
+                    }
+                }
+            }
+            FileOp::ImportCbz => {
+                if let Some((id, coll)) = &mut app.active_collection {
+                    let root = &app.database.collections[id];
+                    match cbz::import(coll, &path, root, &mut app.database.uid_counter) {
+                        Ok((seq, dir)) => {
+                            state
+                                .thumbs_view
+                                .update_from_collection(coll, &state.filter);
+                            egui_state
+                                .sequence_windows
+                                .push(SequenceWindow::new(seq, None));
+                            egui_state
+                                .modal
+                                .success(format!("Imported into {}", dir.display()));
+                        }
+                        Err(e) => egui_state.modal.err(format!("CBZ import failed: {e:?}")),
+                    }
+                }
+            }
+            FileOp::AddRoot => {
+                if let Some((id, coll)) = &mut app.active_collection {
+                    let primary = &app.database.collections[id];
+                    let overlapping = std::iter::once(primary)
+                        .chain(coll.roots.iter().map(|root| &root.path))
+                        .find(|root_path| root::overlaps(&path, root_path));
+                    if let Some(root_path) = overlapping {
+                        egui_state.modal.err(format!(
+                            "{} overlaps the existing root {}",
+                            path.display(),
+                            root_path.display()
+                        ));
+                    } else {
+                        let name = root::default_name(&path);
+                        coll.add_root(name, path, &mut app.database.uid_counter);
+                        // Pick up the files of the new root
+                        app.folder_changes_recv = Some(coll.scan_changes(primary.clone()));
+                    }
+                }
+            }
         }
         egui_state.file_op = None;
     }
@@ -340,7 +470,7 @@ impl egui_sf2g::UserTexSource for TexSrc<'_, '_, '_> {
                 get_tex_for_entry(
                     &self.state.thumbnail_cache,
                     entry::Id(id),
-                    &coll.entries,
+                    coll,
                     &self.state.thumbnail_loader,
                     self.state.thumbs_view.thumb_size,
                     self.res,
@@ -2,6 +2,7 @@ use {
     super::EguiState,
     crate::{
         application::Application, db::FolderChanges, entry, gui::thumbnails_view::ThumbnailsView,
+        root,
     },
     egui_sf2g::{
         egui::{
@@ -11,7 +12,7 @@ use {
         sf2g::graphics::{RenderTarget, RenderWindow},
     },
     fnv::FnvHashMap,
-    std::path::PathBuf,
+    std::path::{Path, PathBuf},
 };
 
 struct AddedInfo {
@@ -22,7 +23,7 @@ struct AddedInfo {
 pub struct ChangesWindow {
     pub open: bool,
     changes: FolderChanges,
-    added: FnvHashMap<PathBuf, AddedInfo>,
+    added: FnvHashMap<(Option<root::Id>, PathBuf), AddedInfo>,
     applied: bool,
 }
 
@@ -42,11 +43,32 @@ pub(super) fn do_frame(
         return;
     }
     let changes = &mut win.changes;
+    let root_names: FnvHashMap<root::Id, String> = app
+        .active_collection
+        .iter()
+        .flat_map(|(_, coll)| &coll.roots)
+        .map(|root| (root.id, root.name.clone()))
+        .collect();
     let mut close = false;
     let mut action = None;
     Window::new("Changes to collection")
         .open(&mut win.open)
         .show(egui_ctx, |ui| {
+            if !changes.unavailable.is_empty() {
+                let names: Vec<_> = changes
+                    .unavailable
+                    .iter()
+                    .map(|root| root_name(&root_names, *root))
+                    .collect();
+                ui.label(
+                    RichText::new(format!(
+                        "Unavailable roots: {}. Their entries are kept as they are.",
+                        names.join(", ")
+                    ))
+                    .color(Color32::YELLOW),
+                );
+                ui.separator();
+            }
             ui.horizontal(|ui| {
                 if !changes.add.is_empty() {
                     ui.vertical(|ui| {
@@ -82,9 +104,12 @@ pub(super) fn do_frame(
                                                     Color32::from_rgb(26, 138, 11);
                                                 vis.hovered.fg_stroke.color =
                                                     Color32::from_rgb(167, 255, 155);
-                                                let label =
-                                                    Label::new(add.to_string_lossy().as_ref())
-                                                        .sense(egui::Sense::click());
+                                                let label = Label::new(display_path(
+                                                    &root_names,
+                                                    add.0,
+                                                    &add.1,
+                                                ))
+                                                .sense(egui::Sense::click());
                                                 ui.add(label).context_menu(|ui| {
                                                     if ui.button("Delete file").clicked() {
                                                         action = Some(Action::RemFile { idx });
@@ -106,9 +131,9 @@ pub(super) fn do_frame(
                             .id_salt("scroll_rm")
                             .auto_shrink(false)
                             .show(ui, |ui| {
-                                for rem in &changes.remove {
+                                for (root, path) in &changes.remove {
                                     let label = Label::new(
-                                        RichText::new(rem.to_string_lossy().as_ref())
+                                        RichText::new(display_path(&root_names, *root, path))
                                             .color(Color32::RED),
                                     );
                                     ui.add(label);
@@ -121,8 +146,8 @@ pub(super) fn do_frame(
             ui.horizontal(|ui| {
                 if !win.applied {
                     if ui.button("Apply").clicked() {
-                        app.apply_changes_to_active_collection(changes, |path, id| {
-                            win.added.insert(path.to_owned(), AddedInfo { id });
+                        app.apply_changes_to_active_collection(changes, |root, path, id| {
+                            win.added.insert((root, path.to_owned()), AddedInfo { id });
                         });
                         if let Some((_, active_coll)) = &mut app.active_collection {
                             state.thumbs_view = ThumbnailsView::from_collection(
@@ -130,7 +155,6 @@ pub(super) fn do_frame(
                                 active_coll,
                                 &state.filter,
                                 &app.database.preferences,
-                                &state.sel,
                             );
                             win.applied = true;
                         } else {
@@ -148,13 +172,17 @@ pub(super) fn do_frame(
     if let Some(action) = action {
         match action {
             Action::RemFile { idx } => {
-                let Some(path) = changes.add.get(idx) else {
+                let Some((root, path)) = changes.add.get(idx) else {
                     egui_state
                         .modal
                         .err(format!("Dangling change index ({idx})"));
                     return;
                 };
-                if let Err(e) = std::fs::remove_file(path) {
+                let result = match &app.active_collection {
+                    Some((_, coll)) => std::fs::remove_file(coll.resolve_path(*root, path)),
+                    None => std::fs::remove_file(path),
+                };
+                if let Err(e) = result {
                     egui_state.modal.err(format!("Failed to remove file: {e}"));
                 }
                 changes.add.remove(idx);
@@ -165,6 +193,25 @@ pub(super) fn do_frame(
         win.open = false;
     }
 }
+fn root_name(root_names: &FnvHashMap<root::Id, String>, root: Option<root::Id>) -> &str {
+    match root {
+        None => root::PRIMARY_NAME,
+        Some(id) => root_names.get(&id).map_or("<dangling>", String::as_str),
+    }
+}
+
+/// Paths of additional roots are prefixed with the name of the root
+fn display_path(
+    root_names: &FnvHashMap<root::Id, String>,
+    root: Option<root::Id>,
+    path: &Path,
+) -> String {
+    match root {
+        None => path.to_string_lossy().into_owned(),
+        Some(_) => format!("[{}] {}", root_name(root_names, root), path.display()),
+    }
+}
+
 impl ChangesWindow {
     /// Opens a freshly initialized changes window containing the provided changes
     pub(crate) fn open_fresh(&mut self, changes: FolderChanges) {
@@ -1,11 +1,13 @@
 use {
     super::{
-        EguiState,
+        EguiState, FileOp,
+        modal::PromptAction,
         tag_autocomplete::{AcState, tag_autocomplete_popup},
     },
     crate::{
         collection::{Collection, TagsExt},
         preferences::{AppMapExt, Preferences},
+        root,
     },
     egui_sf2g::egui,
 };
@@ -18,6 +20,9 @@ pub struct CollPrefsWindow {
     ac_state: AcState,
     ac_closed: bool,
     err_msg: String,
+    new_prop: String,
+    /// Index and new name of the property being renamed
+    prop_rename: Option<(usize, String)>,
 }
 
 #[derive(Default)]
@@ -31,6 +36,9 @@ enum Tab {
     #[default]
     IgnoredExts,
     TagSpecificApps,
+    Props,
+    Archives,
+    Roots,
 }
 
 pub(super) fn do_frame(
@@ -41,6 +49,7 @@ pub(super) fn do_frame(
 ) {
     let win = &mut egui_state.coll_prefs_window;
     let mut open = win.open;
+    let mut roots_action = None;
     egui::Window::new("Collection preferences")
         .open(&mut open)
         .show(egui_ctx, |ui| {
@@ -57,14 +66,99 @@ pub(super) fn do_frame(
                 {
                     win.tab = Tab::TagSpecificApps;
                 }
+                if ui
+                    .selectable_label(win.tab == Tab::Props, "Entry properties")
+                    .clicked()
+                {
+                    win.tab = Tab::Props;
+                }
+                if ui
+                    .selectable_label(win.tab == Tab::Archives, "Archives")
+                    .clicked()
+                {
+                    win.tab = Tab::Archives;
+                }
+                if ui
+                    .selectable_label(win.tab == Tab::Roots, "Roots")
+                    .clicked()
+                {
+                    win.tab = Tab::Roots;
+                }
             });
             ui.separator();
             match win.tab {
                 Tab::IgnoredExts => ignored_exts_ui(ui, coll),
                 Tab::TagSpecificApps => tag_specific_apps_ui(ui, coll, win, prefs),
+                Tab::Props => props_ui(ui, coll, win),
+                Tab::Archives => archives_ui(ui, coll),
+                Tab::Roots => roots_action = roots_ui(ui, coll),
             }
         });
     win.open = open;
+    match roots_action {
+        Some(RootsAction::Add) => {
+            egui_state.file_op = Some(FileOp::AddRoot);
+            egui_state.file_dialog.pick_directory();
+        }
+        Some(RootsAction::Remove(id)) => {
+            let n_entries = coll
+                .entries
+                .values()
+                .filter(|en| en.root == Some(id))
+                .count();
+            egui_state.modal.prompt(
+                "Root removal",
+                format!(
+                    "Remove the root \"{}\" along with its {n_entries} entries?\n\
+                     The files on disk are left alone.",
+                    coll.root_name(Some(id))
+                ),
+                PromptAction::RemoveRoot(id),
+            );
+        }
+        None => {}
+    }
+}
+
+enum RootsAction {
+    Add,
+    Remove(root::Id),
+}
+
+fn roots_ui(ui: &mut egui::Ui, coll: &mut Collection) -> Option<RootsAction> {
+    ui.label(
+        "Folders whose files belong to this collection, in addition to the folder \
+         the collection was loaded from",
+    );
+    ui.separator();
+    let mut action = None;
+    for root in &mut coll.roots {
+        let n_entries = coll
+            .entries
+            .values()
+            .filter(|en| en.root == Some(root.id))
+            .count();
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut root.name).desired_width(120.0));
+            ui.label(root.path.display().to_string());
+            ui.label(format!("({n_entries} entries)"));
+            if !root.path.is_dir() {
+                ui.label(egui::RichText::new("unavailable").color(egui::Color32::YELLOW));
+            }
+            if ui
+                .button("🗑")
+                .on_hover_text("Remove the root and its entries. The files are left alone.")
+                .clicked()
+            {
+                action = Some(RootsAction::Remove(root.id));
+            }
+        });
+    }
+    ui.separator();
+    if ui.button("Add root...").clicked() {
+        action = Some(RootsAction::Add);
+    }
+    action
 }
 
 fn ignored_exts_ui(ui: &mut egui::Ui, coll: &mut Collection) {
@@ -83,6 +177,85 @@ fn ignored_exts_ui(ui: &mut egui::Ui, coll: &mut Collection) {
     }
 }
 
+fn archives_ui(ui: &mut egui::Ui, coll: &mut Collection) {
+    ui.checkbox(&mut coll.browse_archives, "Browse inside zip/cbz archives");
+    ui.label(
+        "The files inside archives become entries, with paths like foo.cbz!/001.png.\n\
+         Takes effect when the folder is reloaded.",
+    );
+}
+
+fn props_ui(ui: &mut egui::Ui, coll: &mut Collection, win: &mut CollPrefsWindow) {
+    ui.label("String properties that entries can have, like source or license");
+    ui.separator();
+    let mut remove = None;
+    let mut rename = None;
+    for (i, name) in coll.prop_schema.iter().enumerate() {
+        let n_used = coll
+            .entries
+            .values()
+            .filter(|en| en.props.contains_key(name))
+            .count();
+        ui.horizontal(|ui| {
+            match &mut win.prop_rename {
+                Some((idx, buf)) if *idx == i => {
+                    let re = ui.text_edit_singleline(buf);
+                    if re.lost_focus() {
+                        rename = Some((name.clone(), buf.clone()));
+                    }
+                    re.request_focus();
+                }
+                _ => {
+                    if ui
+                        .label(name)
+                        .on_hover_text("Double click to rename")
+                        .double_clicked()
+                    {
+                        win.prop_rename = Some((i, name.clone()));
+                    }
+                }
+            }
+            ui.label(format!("({n_used} entries)"));
+            if ui.button("🗑").clicked() {
+                remove = Some(name.clone());
+            }
+        });
+    }
+    if let Some((old, new)) = rename {
+        win.prop_rename = None;
+        win.err_msg.clear();
+        let new = new.trim();
+        if !new.is_empty()
+            && new != old
+            && let Err(e) = coll.rename_prop(&old, new)
+        {
+            win.err_msg = e.to_string();
+        }
+    }
+    if let Some(name) = remove {
+        coll.remove_prop(&name);
+    }
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut win.new_prop);
+        if ui.button("Add new").clicked() {
+            win.err_msg.clear();
+            let name = win.new_prop.trim();
+            if name.is_empty() || name.contains(['=', ' ', '[', ']']) {
+                win.err_msg = format!("Invalid property name: '{name}'");
+            } else if coll.prop_schema.iter().any(|prop| prop == name) {
+                win.err_msg = format!("Property already exists: '{name}'");
+            } else {
+                coll.prop_schema.push(name.to_owned());
+                win.new_prop.clear();
+            }
+        }
+    });
+    if !win.err_msg.is_empty() {
+        ui.label(egui::RichText::new(&win.err_msg).color(egui::Color32::DARK_RED));
+    }
+}
+
 fn tag_specific_apps_ui(
     ui: &mut egui::Ui,
     coll: &mut Collection,
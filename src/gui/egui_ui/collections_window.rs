@@ -1,9 +1,42 @@
-use {crate::application::Application, egui_sf2g::egui};
+use {
+    crate::{
+        application::Application,
+        collection::{self, PrefixRewrite, SampleCheck},
+    },
+    egui_sf2g::egui::{self, Color32, RichText},
+    std::path::PathBuf,
+};
+
+/// Maximum number of missing paths listed after a check
+const MISSING_LIMIT: usize = 5;
 
 #[derive(Default)]
 pub struct CollectionsDbWindow {
     pub open: bool,
-    pub path_assign_id: Option<crate::collection::Id>,
+    pub path_assign_id: Option<collection::Id>,
+    /// Collection to pick a new root for
+    relocate_pick_id: Option<collection::Id>,
+    relocate: Option<Relocate>,
+}
+
+/// A pending relocation of a collection to a new root
+struct Relocate {
+    id: collection::Id,
+    new_root: PathBuf,
+    /// Entry path prefix to replace, for when a subfolder was moved
+    prefix_from: String,
+    prefix_to: String,
+    /// Result of the last check, `None` if something changed since
+    check: Option<anyhow::Result<SampleCheck>>,
+}
+
+impl Relocate {
+    fn rewrite(&self) -> Option<PrefixRewrite> {
+        (!self.prefix_from.is_empty()).then(|| PrefixRewrite {
+            from: self.prefix_from.clone().into(),
+            to: self.prefix_to.clone().into(),
+        })
+    }
 }
 
 pub(crate) fn do_frame(
@@ -11,25 +44,118 @@ pub(crate) fn do_frame(
     egui_state: &mut super::EguiState,
     egui_ctx: &egui::Context,
 ) {
+    let win = &mut egui_state.collections_db_window;
+    let mut relocate_clicked = false;
+    let mut relocate_confirmed = false;
+    let mut relocate_cancelled = false;
     egui::Window::new("Collections database editor")
-        .open(&mut egui_state.collections_db_window.open)
+        .open(&mut win.open)
         .show(egui_ctx, |ui| {
             app.database.collections.retain(|id, path| {
                 let mut retain = true;
                 ui.horizontal(|ui| {
                     ui.label(id.0.to_string());
                     if ui.button(path.display().to_string()).clicked() {
-                        egui_state.collections_db_window.path_assign_id = Some(*id);
+                        win.path_assign_id = Some(*id);
+                        win.relocate_pick_id = None;
                         egui_state.file_dialog.pick_directory();
                     }
+                    if ui
+                        .button("Relocate...")
+                        .on_hover_text("Move the collection to a new root, checking its entries")
+                        .clicked()
+                    {
+                        win.relocate_pick_id = Some(*id);
+                        relocate_clicked = true;
+                    }
                     if ui.button("Remove").clicked() {
                         retain = false;
                     }
                 });
                 retain
             });
+            if let Some(reloc) = &mut win.relocate {
+                ui.separator();
+                ui.heading(format!("Relocate collection {}", reloc.id.0));
+                ui.label(format!("New root: {}", reloc.new_root.display()));
+                ui.horizontal(|ui| {
+                    ui.label("Replace path prefix");
+                    let re1 = ui.text_edit_singleline(&mut reloc.prefix_from);
+                    ui.label("with");
+                    let re2 = ui.text_edit_singleline(&mut reloc.prefix_to);
+                    if re1.changed() || re2.changed() {
+                        reloc.check = None;
+                    }
+                });
+                ui.label("Leave the prefix empty to keep the entry paths as they are");
+                match &reloc.check {
+                    None => {
+                        if ui.button("Check").clicked() {
+                            reloc.check = Some(app.check_relocation(
+                                reloc.id,
+                                &reloc.new_root,
+                                reloc.rewrite().as_ref(),
+                            ));
+                        }
+                    }
+                    Some(Ok(check)) => {
+                        let color = if check.found == check.checked {
+                            Color32::GREEN
+                        } else {
+                            Color32::YELLOW
+                        };
+                        ui.label(
+                            RichText::new(format!(
+                                "{}/{} sampled entries found under the new root",
+                                check.found, check.checked
+                            ))
+                            .color(color),
+                        );
+                        for path in check.missing.iter().take(MISSING_LIMIT) {
+                            ui.label(format!("Missing: {}", path.display()));
+                        }
+                        if check.missing.len() > MISSING_LIMIT {
+                            ui.label(format!(
+                                "...and {} more",
+                                check.missing.len() - MISSING_LIMIT
+                            ));
+                        }
+                    }
+                    Some(Err(e)) => {
+                        ui.label(RichText::new(format!("Check failed: {e}")).color(Color32::RED));
+                    }
+                }
+                ui.horizontal(|ui| {
+                    let checked = matches!(&reloc.check, Some(Ok(check)) if check.found > 0);
+                    if ui
+                        .add_enabled(checked, egui::Button::new("Relocate"))
+                        .clicked()
+                    {
+                        relocate_confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        relocate_cancelled = true;
+                    }
+                });
+            }
         });
-    if let Some(assign_id) = &egui_state.collections_db_window.path_assign_id
+    if relocate_clicked {
+        win.path_assign_id = None;
+        egui_state.file_dialog.pick_directory();
+    }
+    if relocate_cancelled {
+        win.relocate = None;
+    }
+    if relocate_confirmed && let Some(reloc) = win.relocate.take() {
+        let rewrite = reloc.rewrite();
+        match app.relocate_collection(reloc.id, reloc.new_root, rewrite.as_ref()) {
+            Ok(n_rewritten) => egui_state.modal.success(format!(
+                "Collection relocated. {n_rewritten} entry paths rewritten."
+            )),
+            Err(e) => egui_state.modal.err(format!("Failed to relocate: {e:?}")),
+        }
+    }
+    if let Some(assign_id) = &win.path_assign_id
         && let Some(path) = egui_state.file_dialog.take_picked()
     {
         if let Some(coll_path) = app.database.collections.get_mut(assign_id) {
@@ -39,6 +165,20 @@ pub(crate) fn do_frame(
                 .modal
                 .err("Failed to assign path (no such collection)");
         }
-        egui_state.collections_db_window.path_assign_id = None;
+        win.path_assign_id = None;
+    }
+    if let Some(id) = win.relocate_pick_id
+        && let Some(new_root) = egui_state.file_dialog.take_picked()
+    {
+        win.relocate_pick_id = None;
+        let mut reloc = Relocate {
+            id,
+            new_root,
+            prefix_from: String::new(),
+            prefix_to: String::new(),
+            check: None,
+        };
+        reloc.check = Some(app.check_relocation(id, &reloc.new_root, None));
+        win.relocate = Some(reloc);
     }
 }
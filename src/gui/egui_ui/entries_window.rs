@@ -5,12 +5,14 @@ use {
         tag_autocomplete::{AcState, tag_autocomplete_popup},
     },
     crate::{
+        archive,
         collection::{AddTagError, Collection, TagsExt},
+        constraints::{self, Rule},
         db::Db,
         dlog, entry,
         filter_reqs::Requirements,
         gui::{
-            SelectionBufs, State, get_tex_for_entry,
+            State, get_tex_for_entry,
             open::{
                 builtin,
                 external::{self, OpenExternCandidate, feed_args},
@@ -18,7 +20,7 @@ use {
             resources::Resources,
             thumbnails_view::ThumbnailsView,
         },
-        tag,
+        relation, tag,
     },
     anyhow::Context as _,
     constcat::concat,
@@ -53,6 +55,20 @@ pub struct EntriesWindow {
     children: Vec<ChildWrapper>,
     ac_state: AcState,
     window_id: u64,
+    /// Tags waiting for confirmation, because adding them would break tag rules
+    pending_add: Option<PendingAdd>,
+    /// Kind of relation to add to the selected entries
+    relation_kind: relation::Kind,
+    /// Tag suggestions, along with the `(tags_revision, generation, by_dir)` they were made at
+    suggestions: Option<((u64, u64, bool), Vec<tag::Id>)>,
+}
+
+/// A tag to add, optionally with a value
+type TagAdd = (tag::Id, Option<f64>);
+
+struct PendingAdd {
+    tags: Vec<TagAdd>,
+    broken: Vec<Rule>,
 }
 
 struct ChildWrapper {
@@ -95,7 +111,6 @@ fn tag(
     egui_state: &mut EguiState,
     changed_filter: &mut bool,
     entries_view: &mut ThumbnailsView,
-    sel_bufs: &SelectionBufs,
 ) -> Response {
     // FIXME: Magic number to add to text size to stop text from wrapping
     let magic = if del.is_some() { 44.0 } else { 14.0 };
@@ -125,7 +140,7 @@ fn tag(
                     reqs.set_have_tag(id, false);
                     egui_state.filter_popup.string = reqs.to_string(&coll.tags);
                     *changed_filter = true;
-                    entries_view.update_from_collection(coll, reqs, sel_bufs);
+                    entries_view.update_from_collection(coll, reqs);
                 }
                 if ui.button("Open in tags window").clicked() {
                     egui_state.tag_window.toggle();
@@ -137,7 +152,7 @@ fn tag(
                 reqs.set_not_have_tag(id, false);
                 egui_state.filter_popup.string = reqs.to_string(&coll.tags);
                 *changed_filter = true;
-                entries_view.update_from_collection(coll, reqs, sel_bufs);
+                entries_view.update_from_collection(coll, reqs);
             }
             if let Some(del) = del
                 && ui.button(icons::REMOVE).clicked()
@@ -187,6 +202,8 @@ pub(super) fn do_frame(
     res: &Resources,
 ) {
     let mut entries_windows = std::mem::take(&mut egui_state.entries_windows);
+    // Entries to open new windows for, once we're done with the current ones
+    let mut to_open = Vec::new();
     entries_windows.retain_mut(|win| {
         let mut open = true;
         let n_entries = win.ids.len();
@@ -198,7 +215,12 @@ pub(super) fn do_frame(
         let title = {
             if win.ids.len() == 1 {
                 match coll.entries.get(first_entry_id) {
-                    Some(en) => en.path.to_string_lossy().into_owned(),
+                    Some(en) => match en.root {
+                        None => en.path.to_string_lossy().into_owned(),
+                        Some(_) => {
+                            format!("[{}] {}", coll.root_name(en.root), en.path.display())
+                        }
+                    },
                     None => {
                         invalid = true;
                         String::from("<Invalid entry>")
@@ -231,7 +253,7 @@ pub(super) fn do_frame(
                             let tex_size = get_tex_for_entry(
                                 &state.thumbnail_cache,
                                 id,
-                                &coll.entries,
+                                coll,
                                 &state.thumbnail_loader,
                                 state.thumbs_view.thumb_size,
                                 res,
@@ -262,7 +284,7 @@ pub(super) fn do_frame(
                             {
                                 // Can't find in view, open it in external instead
                                 let paths = [OpenExternCandidate {
-                                    path: &entry.path,
+                                    path: coll.entry_path(entry),
                                     open_with: None,
                                 }];
                                 if let Err(e) = external::open(&paths, &mut db.preferences) {
@@ -284,7 +306,8 @@ pub(super) fn do_frame(
                             cross_justify: false,
                         };
                         ui.with_layout(layout, |ui| {
-                            for tagid in crate::entry_utils::common_tags(&win.ids, coll) {
+                            let common = crate::entry_utils::common_tags(&win.ids, coll);
+                            for &tagid in &common {
                                 let tag_name = coll.tags.first_name_of(&tagid);
                                 let mut changed_filter = false;
 
@@ -300,7 +323,6 @@ pub(super) fn do_frame(
                                         egui_state,
                                         &mut changed_filter,
                                         &mut state.thumbs_view,
-                                        &state.sel,
                                     );
                                     if del {
                                         let result = try {
@@ -308,14 +330,11 @@ pub(super) fn do_frame(
                                                 coll.entries
                                                     .get_mut(en_id)
                                                     .context("Failed to get entry")?
-                                                    .tags
-                                                    .retain(|&t| t != tagid);
+                                                    .remove_tag(tagid);
                                             }
-                                            state.thumbs_view.update_from_collection(
-                                                coll,
-                                                &state.filter,
-                                                &state.sel,
-                                            );
+                                            state
+                                                .thumbs_view
+                                                .update_from_collection(coll, &state.filter);
                                         };
                                         if let Err(e) = result {
                                             egui_state
@@ -334,20 +353,38 @@ pub(super) fn do_frame(
                                         egui_state,
                                         &mut changed_filter,
                                         &mut state.thumbs_view,
-                                        &state.sel,
                                     );
                                 }
                                 if changed_filter {
-                                    state.thumbs_view.update_from_collection(
-                                        coll,
-                                        &state.filter,
-                                        &state.sel,
-                                    );
+                                    state
+                                        .thumbs_view
+                                        .update_from_collection(coll, &state.filter);
                                     state.thumbs_view.clamp_bottom(rend_win);
                                 }
                             }
+                            for (tagid, seq) in crate::entry_utils::inherited_tags(&win.ids, coll) {
+                                if common.contains(&tagid) {
+                                    continue;
+                                }
+                                let seq_name = coll
+                                    .sequences
+                                    .get(&seq)
+                                    .map_or("?", |seq| seq.name.as_str());
+                                ui.label(
+                                    RichText::new(coll.tags.first_name_of(&tagid))
+                                        .italics()
+                                        .color(Color32::GRAY),
+                                )
+                                .on_hover_text(format!("Inherited from sequence \"{seq_name}\""));
+                            }
                         });
                         // endregion
+                        suggestions_ui(ui, win, state, egui_state, coll, db);
+                        pending_add_ui(ui, win, state, egui_state, coll);
+                        values_ui(ui, win, state, coll);
+                        props_ui(ui, win, coll);
+                        notes_ui(ui, win, coll);
+                        relations_ui(ui, win, coll, &mut to_open);
 
                         let txt = if win.editing_tags {
                             concat!(icons::CHECK, " Stop editing")
@@ -394,30 +431,22 @@ pub(super) fn do_frame(
                                 close = false;
                             }
                             if re.ctx.input(|inp| inp.key_pressed(Key::Enter)) {
-                                let add_tag_buffer: &str = &win.add_tag_buffer;
-                                let entry_uids: &[entry::Id] = &win.ids;
-                                let tags = add_tag_buffer.split_whitespace();
-                                for tag in tags {
-                                    match coll.resolve_tag(tag) {
-                                        Some(tag_uid) => {
-                                            if let Err(AddTagError) =
-                                                coll.add_tag_for_multi(entry_uids, tag_uid)
-                                            {
-                                                egui_state.modal.err("Failed to add tags");
-                                            }
-                                        }
+                                let mut to_add = Vec::new();
+                                for word in win.add_tag_buffer.split_whitespace() {
+                                    let (name, value) = split_tag_value(word);
+                                    match coll.resolve_tag(name) {
+                                        Some(tag_uid) => to_add.push((tag_uid, value)),
                                         None => {
-                                            win.new_tags.push(tag.to_owned());
+                                            win.new_tags.push(word.to_owned());
                                         }
                                     }
                                 }
+                                add_tags_checked(win, coll, egui_state, to_add);
                                 win.add_tag_buffer.clear();
                                 win.editing_tags = false;
-                                state.thumbs_view.update_from_collection(
-                                    coll,
-                                    &state.filter,
-                                    &state.sel,
-                                );
+                                state
+                                    .thumbs_view
+                                    .update_from_collection(coll, &state.filter);
                             }
                         }
 
@@ -427,20 +456,17 @@ pub(super) fn do_frame(
                                  but they aren't present in the database: ",
                             );
                         }
-                        win.new_tags.retain_mut(|tag| {
+                        win.new_tags.retain_mut(|word| {
                             let mut retain = true;
                             ui.horizontal(|ui| {
-                                ui.label(&tag[..]);
+                                ui.label(&word[..]);
                                 if ui.button("Add").clicked() {
+                                    let (name, value) = split_tag_value(word);
                                     match coll
-                                        .add_new_tag_from_text(tag.to_owned(), &mut db.uid_counter)
+                                        .add_new_tag_from_text(name.to_owned(), &mut db.uid_counter)
                                     {
                                         Some(id) => {
-                                            if let Err(AddTagError) =
-                                                coll.add_tag_for_multi(&win.ids, id)
-                                            {
-                                                egui_state.modal.err("Failed to add tags");
-                                            }
+                                            add_tags(&win.ids, coll, egui_state, &[(id, value)]);
                                             retain = false;
                                         }
                                         None => {
@@ -484,7 +510,7 @@ pub(super) fn do_frame(
                                     match coll.entries.get(uid) {
                                         Some(en) => {
                                             let canonical =
-                                                std::fs::canonicalize(&en.path).how()?;
+                                                std::fs::canonicalize(coll.entry_path(en)).how()?;
                                             writeln!(&mut out, "{}", canonical.display()).how()?;
                                         }
                                         None => {
@@ -516,8 +542,18 @@ pub(super) fn do_frame(
                                 cmd.stdout(Stdio::piped());
                                 for uid in &win.ids {
                                     match coll.entries.get(uid) {
+                                        Some(en) if archive::is_virtual(&en.path) => {
+                                            dlog!(
+                                                "Not passing {} inside an archive",
+                                                en.path.display()
+                                            );
+                                        }
                                         Some(en) => {
-                                            feed_args(&win.args_buffer, &[&en.path], &mut cmd);
+                                            feed_args(
+                                                &win.args_buffer,
+                                                &[&coll.entry_path(en)],
+                                                &mut cmd,
+                                            );
                                         }
                                         None => {
                                             dlog!("No entry with id {uid:?}");
@@ -709,7 +745,7 @@ pub(super) fn do_frame(
                                     .push(SequenceWindow::new(seq_id, None));
                             }
                             if ui.button("Select all").clicked() {
-                                let Some(sel) = state.sel.current_mut() else {
+                                let Some(sel) = coll.sel_bufs.current_mut() else {
                                     dlog!("Couldn't get selection buffer");
                                     return;
                                 };
@@ -729,13 +765,14 @@ pub(super) fn do_frame(
                                         let result = try {
                                             if db.preferences.use_built_in_viewer {
                                                 builtin::open_sequence(
-                                                    state, seq, img_id, rend_win,
+                                                    state, seq_id, seq, img_id, rend_win,
                                                 )?;
                                             } else {
                                                 external::open_sequence(
                                                     seq,
                                                     img_id,
                                                     &coll.entries,
+                                                    &coll.roots,
                                                     &mut db.preferences,
                                                 )?;
                                             }
@@ -759,6 +796,364 @@ pub(super) fn do_frame(
         open
     });
     std::mem::swap(&mut entries_windows, &mut egui_state.entries_windows);
+    for id in to_open {
+        egui_state.add_entries_window(vec![id]);
+    }
+}
+
+/// Number of tag suggestions shown
+const N_SUGGESTIONS: usize = 12;
+
+fn suggestions_ui(
+    ui: &mut Ui,
+    win: &mut EntriesWindow,
+    state: &mut State,
+    egui_state: &mut EguiState,
+    coll: &mut Collection,
+    db: &mut Db,
+) {
+    // Only recount and rank again when something changed
+    let by_dir = db.preferences.tag_suggestions_by_dir;
+    let revision = (coll.tags_revision(), state.thumbs_view.generation());
+    let made_at = (revision.0, revision.1, by_dir);
+    if win
+        .suggestions
+        .as_ref()
+        .is_none_or(|(at, _)| *at != made_at)
+    {
+        state.tag_stats.update_at(&coll.entries, revision);
+        let suggestions =
+            state
+                .tag_stats
+                .suggest(&win.ids, &coll.entries, &coll.tags, by_dir, N_SUGGESTIONS);
+        win.suggestions = Some((made_at, suggestions));
+    }
+    let suggestions = win
+        .suggestions
+        .as_ref()
+        .map_or(Vec::new(), |(_, suggestions)| suggestions.clone());
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Suggested:");
+        if suggestions.is_empty() {
+            ui.label("(nothing)");
+        }
+        for tag_id in suggestions {
+            if ui
+                .small_button(format!(
+                    "{} {}",
+                    icons::ADD,
+                    coll.tags.first_name_of(&tag_id)
+                ))
+                .clicked()
+            {
+                add_tags_checked(win, coll, egui_state, vec![(tag_id, None)]);
+                state
+                    .thumbs_view
+                    .update_from_collection(coll, &state.filter);
+            }
+        }
+    });
+    ui.checkbox(
+        &mut db.preferences.tag_suggestions_by_dir,
+        "Favor tags common in the same folder",
+    );
+}
+
+/// Add `tags` to the entries of the window, unless that would break a tag rule.
+///
+/// In that case, the tags are held back until the user confirms.
+fn add_tags_checked(
+    win: &mut EntriesWindow,
+    coll: &mut Collection,
+    egui_state: &mut EguiState,
+    tags: Vec<TagAdd>,
+) {
+    if tags.is_empty() {
+        return;
+    }
+    let ids: Vec<_> = tags.iter().map(|(id, _)| *id).collect();
+    let broken = constraints::broken_by_adding(coll, &win.ids, &ids);
+    if broken.is_empty() {
+        add_tags(&win.ids, coll, egui_state, &tags);
+    } else {
+        win.pending_add = Some(PendingAdd { tags, broken });
+    }
+}
+
+fn add_tags(ids: &[entry::Id], coll: &mut Collection, egui_state: &mut EguiState, tags: &[TagAdd]) {
+    for &(tag, value) in tags {
+        let result = match value {
+            Some(value) => ids
+                .iter()
+                .try_for_each(|id| coll.set_tag_value(*id, tag, value)),
+            None => coll.add_tag_for_multi(ids, tag),
+        };
+        if let Err(AddTagError) = result {
+            egui_state.modal.err("Failed to add tags");
+        }
+    }
+}
+
+/// Split a `tag=value` word into the tag name and the value
+fn split_tag_value(word: &str) -> (&str, Option<f64>) {
+    match word.split_once('=') {
+        Some((name, value)) => match value.parse() {
+            Ok(value) => (name, Some(value)),
+            Err(_) => (word, None),
+        },
+        None => (word, None),
+    }
+}
+
+/// Editors for the properties in the schema of the collection.
+///
+/// With multiple entries, the value is only shown if every entry has the same value,
+/// and editing it sets it for all of them.
+fn props_ui(ui: &mut Ui, win: &EntriesWindow, coll: &mut Collection) {
+    if coll.prop_schema.is_empty() {
+        return;
+    }
+    let mut set = None;
+    egui::Grid::new(("props_grid", win.window_id))
+        .num_columns(2)
+        .show(ui, |ui| {
+            for key in &coll.prop_schema {
+                let mut values = win.ids.iter().map(|id| {
+                    coll.entries
+                        .get(id)
+                        .and_then(|en| en.props.get(key))
+                        .map_or("", String::as_str)
+                });
+                let first = values.next().unwrap_or_default();
+                let mixed = values.any(|val| val != first);
+                let mut buf = if mixed {
+                    String::new()
+                } else {
+                    first.to_owned()
+                };
+                ui.label(key);
+                let hint = if mixed { "(mixed)" } else { "" };
+                if ui
+                    .add(TextEdit::singleline(&mut buf).hint_text(hint))
+                    .changed()
+                {
+                    set = Some((key.clone(), buf));
+                }
+                ui.end_row();
+            }
+        });
+    if let Some((key, val)) = set {
+        for id in &win.ids {
+            let Some(en) = coll.entries.get_mut(id) else {
+                continue;
+            };
+            if val.is_empty() {
+                en.props.remove(&key);
+            } else {
+                en.props.insert(key.clone(), val.clone());
+            }
+        }
+    }
+}
+
+/// Size of the thumbnails of related entries
+const RELATED_THUMB_SIZE: f32 = 64.0;
+
+/// Relations of the entries, and a form for relating them to the selected entries
+fn relations_ui(
+    ui: &mut Ui,
+    win: &mut EntriesWindow,
+    coll: &mut Collection,
+    to_open: &mut Vec<entry::Id>,
+) {
+    let rels: Vec<_> = coll
+        .relations
+        .iter()
+        .filter(|rel| win.ids.iter().any(|id| rel.involves(*id)))
+        .copied()
+        .collect();
+    let mut remove = None;
+    if !rels.is_empty() {
+        ui.label("Relations");
+        for rel in rels {
+            // Describe the relation from the point of view of the entries in this window
+            let (other, desc) = if win.ids.contains(&rel.from) {
+                (rel.to, rel.kind.name())
+            } else {
+                (rel.from, rel.kind.inverse_name())
+            };
+            ui.horizontal(|ui| {
+                let img = egui::Image::new(SizedTexture::new(
+                    TextureId::User(other.0),
+                    (RELATED_THUMB_SIZE, RELATED_THUMB_SIZE),
+                ));
+                let path = coll
+                    .entries
+                    .get(&other)
+                    .map_or("<dangling>".into(), |en| en.path.to_string_lossy());
+                if ui.add(Button::image(img)).on_hover_text(&*path).clicked() {
+                    to_open.push(other);
+                }
+                ui.label(format!("{desc} {path}"));
+                if ui.button(icons::REMOVE).clicked() {
+                    remove = Some(rel);
+                }
+            });
+        }
+    }
+    if let Some(rel) = remove {
+        coll.relations.retain(|r| *r != rel);
+    }
+    let targets: Vec<entry::Id> = coll
+        .sel_bufs
+        .selected_ids_iter()
+        .filter(|id| !win.ids.contains(id))
+        .copied()
+        .collect();
+    ui.horizontal(|ui| {
+        ui.label("These are");
+        egui::ComboBox::from_id_salt(("relation_kind", win.window_id))
+            .selected_text(win.relation_kind.name())
+            .show_ui(ui, |ui| {
+                for kind in relation::Kind::ALL {
+                    ui.selectable_value(&mut win.relation_kind, kind, kind.name());
+                }
+            });
+        if ui
+            .add_enabled(
+                !targets.is_empty(),
+                Button::new(format!("the {} selected entries", targets.len())),
+            )
+            .on_hover_text("Link to the selected entries")
+            .clicked()
+        {
+            for &from in &win.ids {
+                for &to in &targets {
+                    coll.add_relation(from, win.relation_kind, to);
+                }
+            }
+        }
+    });
+}
+
+/// Notes editor for a single entry, or a summary for multiple entries
+fn notes_ui(ui: &mut Ui, win: &EntriesWindow, coll: &mut Collection) {
+    let &[id] = &win.ids[..] else {
+        let n_noted = win
+            .ids
+            .iter()
+            .filter(|id| coll.entries.get(id).is_some_and(|en| !en.notes.is_empty()))
+            .count();
+        if n_noted > 0 {
+            ui.label(format!("{n_noted} of these entries have notes"));
+        }
+        return;
+    };
+    let Some(en) = coll.entries.get(&id) else {
+        return;
+    };
+    let mut notes = en.notes.clone();
+    let te = TextEdit::multiline(&mut notes)
+        .hint_text("Notes (sources, credits, ...)")
+        .desired_rows(2);
+    if ui.add(te).changed() {
+        coll.set_notes(id, notes);
+    }
+}
+
+/// The values of the tags of the entries. Editable while editing tags.
+fn values_ui(ui: &mut Ui, win: &EntriesWindow, state: &mut State, coll: &mut Collection) {
+    let mut valued: Vec<(tag::Id, f64)> = Vec::new();
+    for id in &win.ids {
+        let Some(en) = coll.entries.get(id) else {
+            continue;
+        };
+        for (tag, val) in &en.tag_values {
+            if !valued.iter().any(|(t, _)| t == tag) {
+                valued.push((*tag, *val));
+            }
+        }
+    }
+    if valued.is_empty() {
+        return;
+    }
+    valued.sort_by_cached_key(|(id, _)| coll.tags.first_name_of(id));
+    let mut changed = false;
+    egui::Grid::new(("tag_values_grid", win.window_id))
+        .num_columns(3)
+        .show(ui, |ui| {
+            for (tag, mut val) in valued {
+                ui.label(coll.tags.first_name_of(&tag));
+                if !win.editing_tags {
+                    ui.label(val.to_string());
+                    ui.end_row();
+                    continue;
+                }
+                let set = ui.add(egui::DragValue::new(&mut val)).changed();
+                let clear = ui
+                    .button(icons::REMOVE)
+                    .on_hover_text("Remove value, keep tag")
+                    .clicked();
+                for id in &win.ids {
+                    let Some(en) = coll.entries.get_mut(id) else {
+                        continue;
+                    };
+                    if clear {
+                        en.tag_values.remove(&tag);
+                    } else if set && en.tags.contains(&tag) {
+                        en.tag_values.insert(tag, val);
+                    }
+                }
+                changed |= set || clear;
+                ui.end_row();
+            }
+        });
+    if changed {
+        state
+            .thumbs_view
+            .update_from_collection(coll, &state.filter);
+    }
+}
+
+fn pending_add_ui(
+    ui: &mut Ui,
+    win: &mut EntriesWindow,
+    state: &mut State,
+    egui_state: &mut EguiState,
+    coll: &mut Collection,
+) {
+    let Some(pending) = &win.pending_add else {
+        return;
+    };
+    let names: Vec<_> = pending
+        .tags
+        .iter()
+        .map(|(id, _)| coll.tags.first_name_of(id))
+        .collect();
+    ui.label(
+        RichText::new(format!(
+            "{} Adding {} would break these rules:",
+            icons::WARN,
+            names.join(" ")
+        ))
+        .color(Color32::YELLOW),
+    );
+    for rule in &pending.broken {
+        ui.label(format!("• {}", rule.describe(&coll.tags)));
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add anyway").clicked()
+            && let Some(pending) = win.pending_add.take()
+        {
+            add_tags(&win.ids, coll, egui_state, &pending.tags);
+            state
+                .thumbs_view
+                .update_from_collection(coll, &state.filter);
+        }
+        if ui.button(icons::CANCEL_TEXT).clicked() {
+            win.pending_add = None;
+        }
+    });
 }
 
 fn remove_entries(
@@ -767,11 +1162,11 @@ fn remove_entries(
     state: &mut State,
 ) -> anyhow::Result<()> {
     for uid in entries.drain(..) {
-        let path = &coll.entries[&uid].path;
-        std::fs::remove_file(path)?;
+        std::fs::remove_file(coll.entry_path(&coll.entries[&uid]))?;
         coll.entries.remove(&uid);
+        coll.relations.retain(|rel| !rel.involves(uid));
         // Also remove from selection buffers, if it's selected
-        state.sel.for_each_mut(|sel| {
+        coll.sel_bufs.for_each_mut(|sel| {
             if let Some(idx) = sel.as_vec().iter().position(|id| *id == uid) {
                 sel.remove(idx);
             }
@@ -780,6 +1175,6 @@ fn remove_entries(
     // Make sure to only update the view after the collection entry removes finished
     state
         .thumbs_view
-        .update_from_collection(coll, &state.filter, &state.sel);
+        .update_from_collection(coll, &state.filter);
     Ok(())
 }
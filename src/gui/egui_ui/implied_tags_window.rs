@@ -0,0 +1,183 @@
+use {
+    super::{EguiState, icons},
+    crate::{
+        collection::{Collection, TagChanges, TagsExt},
+        entry,
+        gui::State,
+        tag,
+    },
+    constcat::concat,
+    egui_sf2g::egui::{self, Color32, RichText, ScrollArea},
+    fnv::FnvHashMap,
+};
+
+/// Maximum number of entries listed in the preview
+const PREVIEW_LIMIT: usize = 500;
+
+#[derive(Default)]
+pub struct ImpliedTagsWindow {
+    pub open: bool,
+    scope: Scope,
+    op: Op,
+    /// Computed changes, waiting to be applied
+    preview: Option<TagChanges>,
+}
+
+#[derive(Default, PartialEq, Eq, Clone, Copy)]
+enum Scope {
+    #[default]
+    Filtered,
+    Selected,
+}
+
+#[derive(Default, PartialEq, Eq, Clone, Copy)]
+enum Op {
+    /// Add every implied tag to the entries explicitly
+    #[default]
+    Materialize,
+    /// Remove explicit tags that another tag of the entry already implies
+    Prune,
+}
+
+pub(super) fn do_frame(
+    state: &mut State,
+    egui_state: &mut EguiState,
+    coll: &mut Collection,
+    egui_ctx: &egui::Context,
+) {
+    let win = &mut egui_state.implied_tags_window;
+    let mut open = win.open;
+    let mut applied = None;
+    egui::Window::new("Implied tags")
+        .open(&mut open)
+        .show(egui_ctx, |ui| {
+            let n_filtered = coll.filter(&state.filter).count();
+            let n_selected = coll.sel_bufs.n_selected();
+            let mut changed = false;
+            ui.horizontal(|ui| {
+                ui.label("Entries");
+                changed |= ui
+                    .radio_value(
+                        &mut win.scope,
+                        Scope::Filtered,
+                        format!("Filtered ({n_filtered})"),
+                    )
+                    .changed();
+                changed |= ui
+                    .radio_value(
+                        &mut win.scope,
+                        Scope::Selected,
+                        format!("Selected ({n_selected})"),
+                    )
+                    .changed();
+            });
+            changed |= ui
+                .radio_value(
+                    &mut win.op,
+                    Op::Materialize,
+                    "Materialize implications (add implied tags explicitly)",
+                )
+                .changed();
+            changed |= ui
+                .radio_value(
+                    &mut win.op,
+                    Op::Prune,
+                    "Prune redundant tags (remove tags another tag already implies)",
+                )
+                .changed();
+            if changed {
+                win.preview = None;
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Preview").clicked() {
+                    let ids: Vec<entry::Id> = match win.scope {
+                        Scope::Filtered => coll.filter(&state.filter).collect(),
+                        Scope::Selected => coll.sel_bufs.selected_ids_iter().copied().collect(),
+                    };
+                    win.preview = Some(match win.op {
+                        Op::Materialize => coll.implied_tags_to_add(&ids),
+                        Op::Prune => coll.redundant_tags(&ids),
+                    });
+                }
+                if ui
+                    .add_enabled(
+                        win.preview
+                            .as_ref()
+                            .is_some_and(|changes| !changes.is_empty()),
+                        egui::Button::new(concat!(icons::CHECK, " Apply")),
+                    )
+                    .clicked()
+                    && let Some(previewed) = win.preview.take()
+                {
+                    // Tags or implications may have changed since the preview,
+                    // so only apply what still holds
+                    let ids: Vec<entry::Id> = previewed.iter().map(|(id, _)| *id).collect();
+                    let current = match win.op {
+                        Op::Materialize => coll.implied_tags_to_add(&ids),
+                        Op::Prune => coll.redundant_tags(&ids),
+                    };
+                    let changes = still_valid(previewed, &current);
+                    match win.op {
+                        Op::Materialize => coll.add_tag_changes(&changes),
+                        Op::Prune => coll.remove_tag_changes(&changes),
+                    }
+                    applied = Some(changes);
+                }
+            });
+            let Some(changes) = &win.preview else {
+                return;
+            };
+            ui.separator();
+            let n_tags: usize = changes.iter().map(|(_, tags)| tags.len()).sum();
+            let (sign, color, verb) = match win.op {
+                Op::Materialize => ("+", Color32::GREEN, "added to"),
+                Op::Prune => ("-", Color32::RED, "removed from"),
+            };
+            ui.label(format!(
+                "{n_tags} tags will be {verb} {} entries",
+                changes.len()
+            ));
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                for (id, tags) in changes.iter().take(PREVIEW_LIMIT) {
+                    ui.horizontal_wrapped(|ui| {
+                        match coll.entries.get(id) {
+                            Some(en) => ui.label(en.path.display().to_string()),
+                            None => ui.label(format!("<dangling:{id:?}>")),
+                        };
+                        for tag in tags {
+                            ui.label(
+                                RichText::new(format!("{sign}{}", coll.tags.first_name_of(tag)))
+                                    .color(color),
+                            );
+                        }
+                    });
+                }
+                if changes.len() > PREVIEW_LIMIT {
+                    ui.label(format!("...and {} more", changes.len() - PREVIEW_LIMIT));
+                }
+            });
+        });
+    win.open = open;
+    if let Some(changes) = applied {
+        state
+            .thumbs_view
+            .update_from_collection(coll, &state.filter);
+        egui_state
+            .modal
+            .success(format!("Changed the tags of {} entries", changes.len()));
+    }
+}
+
+/// The changes of `previewed` that are also part of `current`
+fn still_valid(previewed: TagChanges, current: &TagChanges) -> TagChanges {
+    let current: FnvHashMap<entry::Id, &Vec<tag::Id>> =
+        current.iter().map(|(id, tags)| (*id, tags)).collect();
+    previewed
+        .into_iter()
+        .filter_map(|(id, mut tags)| {
+            let now = current.get(&id)?;
+            tags.retain(|tag| now.contains(tag));
+            (!tags.is_empty()).then_some((id, tags))
+        })
+        .collect()
+}
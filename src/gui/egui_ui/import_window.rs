@@ -0,0 +1,215 @@
+use {
+    super::EguiState,
+    crate::{
+        application::Application,
+        collection,
+        gui::State,
+        merge::{self, Pending, Plan, Poll},
+    },
+    anyhow::Context as _,
+    egui_sf2g::egui::{self, ComboBox, Grid, ScrollArea},
+};
+
+#[derive(Default)]
+pub struct ImportWindow {
+    pub open: bool,
+    source: Option<collection::Id>,
+    pending: Option<Pending>,
+    plan: Option<Plan>,
+}
+
+pub(super) fn do_frame(
+    state: &mut State,
+    egui_state: &mut EguiState,
+    app: &mut Application,
+    egui_ctx: &egui::Context,
+) {
+    let win = &mut egui_state.import_window;
+    if !win.open {
+        return;
+    }
+    let Some((coll_id, coll)) = &app.active_collection else {
+        return;
+    };
+    // The active collection changed since the analysis
+    if win
+        .pending
+        .as_ref()
+        .is_some_and(|pending| pending.dst != *coll_id)
+        || win.plan.as_ref().is_some_and(|plan| plan.dst != *coll_id)
+    {
+        win.pending = None;
+        win.plan = None;
+    }
+    if win.source == Some(*coll_id) {
+        win.source = None;
+    }
+    if let Some(pending) = win.pending.take() {
+        match pending.poll(coll) {
+            Poll::Running(pending) => win.pending = Some(pending),
+            Poll::Done(plan) => win.plan = Some(plan),
+            Poll::Failed => egui_state
+                .modal
+                .err("Failed to analyze: comparison thread died"),
+        }
+    }
+    let mut open = win.open;
+    let mut analyze = false;
+    let mut import = false;
+    egui::Window::new("Import from collection")
+        .open(&mut open)
+        .show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Source");
+                let label = |id: collection::Id| {
+                    app.database
+                        .collections
+                        .get(&id)
+                        .map_or_else(|| format!("{}", id.0), |path| path.display().to_string())
+                };
+                ComboBox::new("import_source", "")
+                    .selected_text(win.source.map(label).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for id in app.database.collections.keys() {
+                            if id != coll_id
+                                && ui
+                                    .selectable_label(win.source == Some(*id), label(*id))
+                                    .clicked()
+                            {
+                                win.source = Some(*id);
+                                win.pending = None;
+                                win.plan = None;
+                            }
+                        }
+                    });
+                if ui
+                    .add_enabled(
+                        win.source.is_some() && win.pending.is_none(),
+                        egui::Button::new("Analyze"),
+                    )
+                    .on_hover_text(
+                        "Entries outside of this collection's roots are compared by content, \
+                         which can take a while",
+                    )
+                    .clicked()
+                {
+                    analyze = true;
+                }
+            });
+            if win.pending.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Comparing contents...");
+                });
+            }
+            let Some(plan) = &mut win.plan else {
+                return;
+            };
+            ui.separator();
+            let stats = &plan.stats;
+            Grid::new("import_stats").show(ui, |ui| {
+                for (label, n) in [
+                    ("Matched by path", stats.matched_by_path),
+                    ("Matched by content", stats.matched_by_content),
+                    ("New entries", stats.new_entries),
+                    ("New roots", stats.new_roots),
+                    ("New tags", stats.new_tags),
+                    ("Sequences", stats.sequences),
+                    ("Skipped (overlapping roots)", stats.skipped),
+                ] {
+                    ui.label(label);
+                    ui.label(n.to_string());
+                    ui.end_row();
+                }
+            });
+            if !plan.conflicts.is_empty() {
+                ui.separator();
+                ui.heading(format!("Conflicts ({})", plan.conflicts.len()));
+                ui.horizontal(|ui| {
+                    if ui.button("Keep all mine").clicked() {
+                        plan.conflicts
+                            .iter_mut()
+                            .for_each(|c| c.take_theirs = false);
+                    }
+                    if ui.button("Take all theirs").clicked() {
+                        plan.conflicts.iter_mut().for_each(|c| c.take_theirs = true);
+                    }
+                });
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    Grid::new("import_conflicts").striped(true).show(ui, |ui| {
+                        for conflict in &mut plan.conflicts {
+                            let path = coll
+                                .entries
+                                .get(&conflict.dst)
+                                .map_or("<dangling>".into(), |en| en.path.to_string_lossy());
+                            ui.label(path);
+                            ui.label(conflict.field.name());
+                            ui.radio_value(&mut conflict.take_theirs, false, &conflict.mine);
+                            ui.radio_value(&mut conflict.take_theirs, true, &conflict.theirs);
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+            ui.separator();
+            if ui.button("Import").clicked() {
+                import = true;
+            }
+        });
+    win.open = open;
+    if analyze && let Some(src_id) = win.source {
+        let result = try {
+            let src = app.read_collection(src_id)?;
+            let (dst_id, dst) = app
+                .active_collection
+                .as_ref()
+                .context("No active collection")?;
+            let src_root = app
+                .database
+                .collections
+                .get(&src_id)
+                .context("Dangling collection")?;
+            let dst_root = app
+                .database
+                .collections
+                .get(dst_id)
+                .context("Dangling collection")?;
+            merge::analyze(*dst_id, dst, dst_root, src, src_root)
+        };
+        match result {
+            Ok(pending) => {
+                win.plan = None;
+                win.pending = Some(pending);
+            }
+            Err(e) => egui_state.modal.err(format!("Failed to analyze: {e:?}")),
+        }
+    }
+    if import
+        && let Some(plan) = win.plan.take()
+        && let Some((coll_id, coll)) = &mut app.active_collection
+    {
+        let result: anyhow::Result<()> = try {
+            let dst_root = app
+                .database
+                .collections
+                .get(coll_id)
+                .context("Dangling collection")?;
+            merge::apply(
+                *coll_id,
+                coll,
+                dst_root,
+                plan,
+                &mut app.database.uid_counter,
+            )?;
+        };
+        match result {
+            Ok(()) => {
+                state
+                    .thumbs_view
+                    .update_from_collection(coll, &state.filter);
+                egui_state.modal.success("Import finished");
+            }
+            Err(e) => egui_state.modal.err(format!("Failed to import: {e:?}")),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use {
     super::icons,
-    crate::{dlog, tag},
+    crate::{dlog, root, tag},
     constcat::concat,
     egui_flex::{Flex, FlexAlign, FlexAlignContent, item},
     egui_sf2g::egui::{self, TextWrapMode},
@@ -11,7 +11,14 @@ use {
 pub enum PromptAction {
     QuitNoSave,
     DeleteTags(Vec<tag::Id>),
-    MergeTag { merge: tag::Id, into: tag::Id },
+    MergeTag {
+        merge: tag::Id,
+        into: tag::Id,
+    },
+    /// Merge each `(merge, into)` pair, in order
+    MergeTags(Vec<(tag::Id, tag::Id)>),
+    /// Remove a root of the active collection, along with its entries
+    RemoveRoot(root::Id),
     PanicTest,
 }
 
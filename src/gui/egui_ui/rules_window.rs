@@ -0,0 +1,181 @@
+use {
+    super::{EguiState, icons},
+    crate::{
+        collection::Collection,
+        constraints::{self, Rule},
+        entry,
+        gui::State,
+    },
+    constcat::concat,
+    egui_sf2g::egui::{self, Color32, Grid, RichText, ScrollArea},
+};
+
+/// Maximum number of violations listed
+const VIOLATION_LIMIT: usize = 500;
+
+/// The query that matches entries violating any rule
+const VIOLATIONS_QUERY: &str = "@violations";
+
+#[derive(Default)]
+pub struct RulesWindow {
+    pub open: bool,
+    new_kind: NewKind,
+    new_a: String,
+    new_b: String,
+    /// Result of the last check
+    violations: Option<Vec<(entry::Id, Rule)>>,
+}
+
+#[derive(Default, PartialEq, Eq, Clone, Copy)]
+enum NewKind {
+    #[default]
+    Excludes,
+    RequiresSeq,
+}
+
+impl RulesWindow {
+    fn new_rule(&self, coll: &Collection) -> anyhow::Result<Rule> {
+        let resolve = |name: &str| {
+            coll.resolve_tag(name.trim())
+                .ok_or_else(|| anyhow::anyhow!("No such tag: {}", name.trim()))
+        };
+        Ok(match self.new_kind {
+            NewKind::Excludes => {
+                let a = resolve(&self.new_a)?;
+                let b = resolve(&self.new_b)?;
+                anyhow::ensure!(a != b, "A tag can't exclude itself");
+                Rule::Excludes(a, b)
+            }
+            NewKind::RequiresSeq => Rule::RequiresSeq(resolve(&self.new_a)?),
+        })
+    }
+}
+
+pub(super) fn do_frame(
+    state: &mut State,
+    egui_state: &mut EguiState,
+    coll: &mut Collection,
+    egui_ctx: &egui::Context,
+) {
+    let win = &mut egui_state.rules_window;
+    if !win.open {
+        return;
+    }
+    let mut open = win.open;
+    let mut open_entry = None;
+    let mut show_violations = false;
+    let mut err = None;
+    egui::Window::new("Tag rules")
+        .open(&mut open)
+        .show(egui_ctx, |ui| {
+            let mut remove = None;
+            if coll.rules.is_empty() {
+                ui.label("No rules yet");
+            }
+            for (i, rule) in coll.rules.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(icons::REMOVE)
+                        .on_hover_text("Delete rule")
+                        .clicked()
+                    {
+                        remove = Some(i);
+                    }
+                    ui.label(rule.describe(&coll.tags));
+                });
+            }
+            if let Some(i) = remove {
+                coll.rules.remove(i);
+                win.violations = None;
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut win.new_kind, NewKind::Excludes, "Excludes");
+                ui.radio_value(&mut win.new_kind, NewKind::RequiresSeq, "Requires sequence");
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut win.new_a).hint_text("Tag"));
+                if win.new_kind == NewKind::Excludes {
+                    ui.label("excludes");
+                    ui.add(egui::TextEdit::singleline(&mut win.new_b).hint_text("Tag"));
+                }
+                if ui.button(concat!(icons::ADD, " Add rule")).clicked() {
+                    match win.new_rule(coll) {
+                        Ok(rule) => {
+                            if !coll.rules.contains(&rule) {
+                                coll.rules.push(rule);
+                            }
+                            win.new_a.clear();
+                            win.new_b.clear();
+                            win.violations = None;
+                        }
+                        Err(e) => err = Some(e.to_string()),
+                    }
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Check").clicked() {
+                    win.violations = Some(constraints::violations(coll));
+                }
+                if ui
+                    .button("Filter violating entries")
+                    .on_hover_text(VIOLATIONS_QUERY)
+                    .clicked()
+                {
+                    show_violations = true;
+                }
+            });
+            let Some(violations) = &win.violations else {
+                return;
+            };
+            if violations.is_empty() {
+                ui.label(RichText::new("No violations").color(Color32::GREEN));
+                return;
+            }
+            ui.label(format!("{} violations", violations.len()));
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                Grid::new("rule_violations_grid")
+                    .striped(true)
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (id, rule) in violations.iter().take(VIOLATION_LIMIT) {
+                            let path = coll
+                                .entries
+                                .get(id)
+                                .map_or("<dangling>".into(), |en| en.path.to_string_lossy());
+                            if ui.link(path).clicked() {
+                                open_entry = Some(*id);
+                            }
+                            ui.label(RichText::new(rule.describe(&coll.tags)).color(Color32::RED));
+                            ui.end_row();
+                        }
+                    });
+                if violations.len() > VIOLATION_LIMIT {
+                    ui.label(format!(
+                        "...and {} more",
+                        violations.len() - VIOLATION_LIMIT
+                    ));
+                }
+            });
+        });
+    win.open = open;
+    if let Some(msg) = err {
+        egui_state.modal.err(msg);
+    }
+    if show_violations {
+        match state.filter.parse_and_resolve(VIOLATIONS_QUERY, coll) {
+            Ok(()) => {
+                egui_state.filter_popup.string = VIOLATIONS_QUERY.into();
+                state.wipe_search();
+                state
+                    .thumbs_view
+                    .update_from_collection(coll, &state.filter);
+            }
+            Err(e) => egui_state.modal.err(format!("Filter error: {e}")),
+        }
+    }
+    if let Some(id) = open_entry {
+        egui_state.add_entries_window(vec![id]);
+    }
+}
@@ -0,0 +1,142 @@
+use {
+    super::{EguiState, sequences::SequenceWindow},
+    crate::{
+        collection::Collection,
+        db::UidCounter,
+        seq_detect::{self, Candidate},
+    },
+    egui_sf2g::egui::{self, CollapsingHeader, ScrollArea, TextEdit},
+};
+
+/// Maximum number of filenames shown in the preview of a candidate
+const PREVIEW_LIMIT: usize = 100;
+
+#[derive(Default)]
+pub struct SeqDetectWindow {
+    pub open: bool,
+    /// Result of the last scan, along with whether each candidate is accepted
+    candidates: Option<Vec<(Candidate, bool)>>,
+    /// Open a sequence window for each created sequence
+    open_created: bool,
+}
+
+pub(super) fn do_frame(
+    egui_state: &mut EguiState,
+    coll: &mut Collection,
+    uid_counter: &mut UidCounter,
+    egui_ctx: &egui::Context,
+) {
+    let win = &mut egui_state.seq_detect_window;
+    if !win.open {
+        return;
+    }
+    let mut open = win.open;
+    let mut create = false;
+    egui::Window::new("Detect sequences")
+        .open(&mut open)
+        .show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Scan")
+                    .on_hover_text(
+                        "Group entries in the same directory by the numbering of their filenames",
+                    )
+                    .clicked()
+                {
+                    win.candidates = Some(
+                        seq_detect::detect(coll)
+                            .into_iter()
+                            .map(|cand| (cand, true))
+                            .collect(),
+                    );
+                }
+                ui.checkbox(&mut win.open_created, "Open created sequences");
+            });
+            let Some(candidates) = &mut win.candidates else {
+                ui.label("Entries that are already in a sequence are left out");
+                return;
+            };
+            if candidates.is_empty() {
+                ui.label("Nothing found");
+                return;
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Accept all").clicked() {
+                    candidates.iter_mut().for_each(|(_, acc)| *acc = true);
+                }
+                if ui.button("Reject all").clicked() {
+                    candidates.iter_mut().for_each(|(_, acc)| *acc = false);
+                }
+            });
+            ui.separator();
+            ScrollArea::vertical().max_height(500.0).show(ui, |ui| {
+                for (i, (cand, accepted)) in candidates.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(accepted, "");
+                        ui.add(TextEdit::singleline(&mut cand.name).desired_width(200.0));
+                        ui.label(format!(
+                            "{} entries in {}",
+                            cand.entries.len(),
+                            cand.dir.display()
+                        ));
+                    });
+                    CollapsingHeader::new("Order")
+                        .id_salt(("seq_detect_order", i))
+                        .show(ui, |ui| {
+                            for id in cand.entries.iter().take(PREVIEW_LIMIT) {
+                                if let Some(en) = coll.entries.get(id) {
+                                    ui.label(
+                                        en.path.file_name().map_or_else(Default::default, |name| {
+                                            name.to_string_lossy()
+                                        }),
+                                    );
+                                }
+                            }
+                            if cand.entries.len() > PREVIEW_LIMIT {
+                                ui.label(format!(
+                                    "...and {} more",
+                                    cand.entries.len() - PREVIEW_LIMIT
+                                ));
+                            }
+                        });
+                }
+            });
+            ui.separator();
+            let n_accepted = candidates.iter().filter(|(_, acc)| *acc).count();
+            if ui
+                .add_enabled(
+                    n_accepted > 0,
+                    egui::Button::new(format!("Create {n_accepted} sequences")),
+                )
+                .clicked()
+            {
+                create = true;
+            }
+        });
+    win.open = open;
+    if !create {
+        return;
+    }
+    let Some(candidates) = win.candidates.take() else {
+        return;
+    };
+    let open_created = win.open_created;
+    for (cand, _) in candidates.into_iter().filter(|(_, acc)| *acc) {
+        // Entries could have been removed since the scan
+        let entries: Vec<_> = cand
+            .entries
+            .into_iter()
+            .filter(|id| coll.entries.contains_key(id))
+            .collect();
+        if entries.is_empty() {
+            continue;
+        }
+        let uid = coll.add_new_sequence(&cand.name, uid_counter);
+        coll.add_entries_to_sequence(uid, &entries);
+        if open_created {
+            egui_state
+                .sequence_windows
+                .push(SequenceWindow::new(uid, None));
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use {
-    super::{EguiState, icons},
+    super::{EguiState, FileOp, icons},
     crate::{
-        collection::Collection,
+        cbz,
+        collection::{Collection, TagsExt},
         db::UidCounter,
         dlog, entry,
         gui::{
@@ -13,21 +14,31 @@ use {
         },
         preferences::Preferences,
         sequence::{self},
+        tag,
     },
+    anyhow::Context as _,
     constcat::concat,
     egui_sf2g::{
         egui::{
-            self, Align, Button, Color32, Context, DragValue, Key, ScrollArea, TextEdit, TextureId,
-            Window, load::SizedTexture,
+            self, Align, Button, CollapsingHeader, Color32, ComboBox, Context, DragValue, Key,
+            ScrollArea, StrokeKind, TextEdit, TextureId, Window, load::SizedTexture,
         },
         sf2g::graphics::RenderWindow,
     },
 };
 
+/// Drag and drop payload for reordering the entries of a sequence
+#[derive(Clone, Copy)]
+struct SeqDrag {
+    seq: sequence::Id,
+    entry: entry::Id,
+}
+
 pub(super) fn do_sequence_windows(
     state: &mut State,
     egui_state: &mut EguiState,
     coll: &mut Collection,
+    uid_counter: &mut UidCounter,
     egui_ctx: &Context,
     prefs: &mut Preferences,
     window: &RenderWindow,
@@ -42,8 +53,31 @@ pub(super) fn do_sequence_windows(
         Open,
     }
 
+    /// Operations on the whole sequence
+    enum SeqOp {
+        NaturalSort,
+        Reverse,
+        Dedupe,
+        Split(usize),
+        Merge(sequence::Id),
+        SetCover(entry::Id),
+        AddBookmark(entry::Id),
+        RemoveBookmark(usize),
+        ExportCbz,
+        AddTag(String),
+        RemoveTag(tag::Id),
+    }
+
+    let mut new_windows = Vec::new();
     egui_state.sequence_windows.retain_mut(|win| {
         let mut open = true;
+        let mut others: Vec<_> = coll
+            .sequences
+            .iter()
+            .filter(|(uid, _)| **uid != win.uid)
+            .map(|(uid, seq)| (*uid, seq.name.clone()))
+            .collect();
+        others.sort_by(|(_, a), (_, b)| a.cmp(b));
         let Some(seq) = coll.sequences.get_mut(&win.uid) else {
             dlog!("Can't get sequence {:?}", win.uid);
             return false;
@@ -51,6 +85,7 @@ pub(super) fn do_sequence_windows(
         let name = &seq.name;
         let mut action = Action::SwapLeft;
         let mut subject = None;
+        let mut seq_op = None;
         if egui_ctx.input(|inp| inp.key_pressed(Key::Escape)) {
             open = false;
             egui_state.just_closed_window_with_esc = true;
@@ -60,6 +95,99 @@ pub(super) fn do_sequence_windows(
             .min_width(3. * 256.)
             .open(&mut open)
             .show(egui_ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("🔤 Natural sort")
+                        .on_hover_text("Sort by filename, with numbers in numeric order")
+                        .clicked()
+                    {
+                        seq_op = Some(SeqOp::NaturalSort);
+                    }
+                    if ui.button("⇄ Reverse").clicked() {
+                        seq_op = Some(SeqOp::Reverse);
+                    }
+                    if ui.button("📦 Export CBZ...").clicked() {
+                        seq_op = Some(SeqOp::ExportCbz);
+                    }
+                    let n_dups = seq.n_duplicates();
+                    if ui
+                        .add_enabled(
+                            n_dups > 0,
+                            Button::new(format!("Remove {n_dups} duplicates")),
+                        )
+                        .clicked()
+                    {
+                        seq_op = Some(SeqOp::Dedupe);
+                    }
+                    ui.separator();
+                    let merge_name = win
+                        .merge_from
+                        .and_then(|id| others.iter().find(|(uid, _)| *uid == id))
+                        .map_or("", |(_, name)| name.as_str());
+                    ComboBox::from_id_salt(("seq_merge_from", win.uid.0))
+                        .selected_text(merge_name)
+                        .show_ui(ui, |ui| {
+                            for (uid, name) in &others {
+                                ui.selectable_value(&mut win.merge_from, Some(*uid), name);
+                            }
+                        });
+                    if ui
+                        .add_enabled(win.merge_from.is_some(), Button::new("Merge into this"))
+                        .on_hover_text("Append the entries of the other sequence, and delete it")
+                        .clicked()
+                        && let Some(from) = win.merge_from.take()
+                    {
+                        seq_op = Some(SeqOp::Merge(from));
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Tags")
+                        .on_hover_text("Every entry of the sequence inherits these tags");
+                    for &tag in &seq.tags {
+                        ui.label(coll.tags.first_name_of(&tag));
+                        if ui
+                            .small_button(icons::REMOVE)
+                            .on_hover_text("Remove tag from sequence")
+                            .clicked()
+                        {
+                            seq_op = Some(SeqOp::RemoveTag(tag));
+                        }
+                    }
+                    let re = ui.add(
+                        TextEdit::singleline(&mut win.tag_input)
+                            .hint_text("Add tag")
+                            .desired_width(120.0),
+                    );
+                    if re.lost_focus()
+                        && ui.input(|inp| inp.key_pressed(Key::Enter))
+                        && !win.tag_input.trim().is_empty()
+                    {
+                        seq_op = Some(SeqOp::AddTag(std::mem::take(&mut win.tag_input)));
+                    }
+                });
+                if !seq.bookmarks.is_empty() {
+                    CollapsingHeader::new(format!("🔖 Bookmarks ({})", seq.bookmarks.len()))
+                        .id_salt(("seq_bookmarks", win.uid.0))
+                        .show(ui, |ui| {
+                            for (i, bm) in seq.bookmarks.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.add(TextEdit::singleline(&mut bm.name).desired_width(160.0));
+                                    if ui.button("▶ Open").clicked() {
+                                        action = Action::Open;
+                                        subject = Some(bm.entry);
+                                    }
+                                    if ui
+                                        .small_button(icons::REMOVE)
+                                        .on_hover_text("Delete bookmark")
+                                        .clicked()
+                                    {
+                                        seq_op = Some(SeqOp::RemoveBookmark(i));
+                                    }
+                                });
+                            }
+                        });
+                }
+                ui.separator();
                 ui.horizontal(|ui| {
                     let seq_entries_len = seq.entries.len();
                     for (i, &img_uid) in seq.entries.iter().enumerate() {
@@ -71,7 +199,36 @@ pub(super) fn do_sequence_windows(
                             if win.focus_req == Some(img_uid) {
                                 img = img.tint(Color32::YELLOW);
                             }
-                            let re = ui.add(Button::image(img));
+                            let drag = SeqDrag {
+                                seq: win.uid,
+                                entry: img_uid,
+                            };
+                            let drag_re = ui.dnd_drag_source(
+                                egui::Id::new("seq_drag").with((win.uid.0, i)),
+                                drag,
+                                |ui| ui.add(Button::image(img)),
+                            );
+                            let re = drag_re.inner;
+                            if drag_re
+                                .response
+                                .dnd_hover_payload::<SeqDrag>()
+                                .is_some_and(|payload| payload.seq == win.uid)
+                            {
+                                ui.painter().rect_stroke(
+                                    drag_re.response.rect,
+                                    egui::CornerRadius::same(2),
+                                    (2.0, Color32::YELLOW),
+                                    StrokeKind::Outside,
+                                );
+                            }
+                            if let Some(payload) = drag_re.response.dnd_release_payload::<SeqDrag>()
+                                && payload.seq == win.uid
+                                && payload.entry != img_uid
+                            {
+                                action = Action::SwapAt(i);
+                                subject = Some(payload.entry);
+                                win.focus_req = subject;
+                            }
                             if win.focus_req == Some(img_uid) {
                                 re.scroll_to_me(Some(Align::Center));
                                 win.focus_req = None;
@@ -80,7 +237,10 @@ pub(super) fn do_sequence_windows(
                                 action = Action::Open;
                                 subject = Some(img_uid);
                             }
-                            ui.label(coll.entries[&img_uid].path.to_string_lossy().as_ref());
+                            match coll.entries.get(&img_uid) {
+                                Some(en) => ui.label(en.path.to_string_lossy().as_ref()),
+                                None => ui.label(format!("<dangling:{img_uid:?}>")),
+                            };
                             ui.horizontal(|ui| {
                                 let mut pos = i;
                                 let dv = DragValue::new(&mut pos).range(0..=seq.entries.len() - 1);
@@ -126,6 +286,28 @@ pub(super) fn do_sequence_windows(
                                     subject = Some(img_uid);
                                     win.focus_req = subject;
                                 }
+                                if ui
+                                    .add_enabled(i > 0, Button::new("✂"))
+                                    .on_hover_text("Split the sequence here")
+                                    .clicked()
+                                {
+                                    seq_op = Some(SeqOp::Split(i));
+                                }
+                                let is_cover = seq.cover() == Some(img_uid);
+                                if ui
+                                    .add_enabled(!is_cover, Button::new("🖼"))
+                                    .on_hover_text(if is_cover {
+                                        "This is the cover"
+                                    } else {
+                                        "Use as cover"
+                                    })
+                                    .clicked()
+                                {
+                                    seq_op = Some(SeqOp::SetCover(img_uid));
+                                }
+                                if ui.button("🔖").on_hover_text("Bookmark").clicked() {
+                                    seq_op = Some(SeqOp::AddBookmark(img_uid));
+                                }
                             });
                         });
                     }
@@ -154,9 +336,9 @@ pub(super) fn do_sequence_windows(
                 Action::Open => {
                     let result = try {
                         if prefs.use_built_in_viewer {
-                            builtin::open_sequence(state, seq, uid, window)?;
+                            builtin::open_sequence(state, win.uid, seq, uid, window)?;
                         } else {
-                            external::open_sequence(seq, uid, &coll.entries, prefs)?;
+                            external::open_sequence(seq, uid, &coll.entries, &coll.roots, prefs)?;
                         }
                     };
                     if let Err(e) = result {
@@ -167,8 +349,70 @@ pub(super) fn do_sequence_windows(
                 }
             }
         }
+        if let Some(op) = seq_op {
+            let result: anyhow::Result<()> = try {
+                match op {
+                    SeqOp::NaturalSort => {
+                        let mut entries = std::mem::take(&mut seq.entries);
+                        coll.sort_naturally(&mut entries);
+                        if let Some(seq) = coll.sequences.get_mut(&win.uid) {
+                            seq.entries = entries;
+                        }
+                    }
+                    SeqOp::Reverse => seq.reverse(),
+                    SeqOp::Dedupe => {
+                        seq.dedupe();
+                    }
+                    SeqOp::Split(at) => {
+                        let new = coll.split_sequence(win.uid, at, uid_counter)?;
+                        new_windows.push(SequenceWindow::new(new, None));
+                    }
+                    SeqOp::Merge(from) => {
+                        coll.merge_sequences(win.uid, from)?;
+                    }
+                    SeqOp::SetCover(id) => seq.cover = Some(id),
+                    SeqOp::AddBookmark(id) => seq.add_bookmark(id),
+                    SeqOp::ExportCbz => {
+                        egui_state.file_op = Some(FileOp::ExportCbz(cbz::Export::sequence(
+                            seq,
+                            egui_state.cbz_comic_info,
+                        )));
+                        egui_state.file_dialog.save_file();
+                    }
+                    SeqOp::RemoveBookmark(i) => {
+                        if i < seq.bookmarks.len() {
+                            seq.bookmarks.remove(i);
+                        }
+                    }
+                    SeqOp::AddTag(name) => {
+                        let tag = coll
+                            .resolve_tag(name.trim())
+                            .with_context(|| format!("No such tag: {}", name.trim()))?;
+                        coll.sequences
+                            .get_mut(&win.uid)
+                            .context("Sequence disappeared")?
+                            .add_tag(tag);
+                        state
+                            .thumbs_view
+                            .update_from_collection(coll, &state.filter);
+                    }
+                    SeqOp::RemoveTag(tag) => {
+                        seq.tags.retain(|t| *t != tag);
+                        state
+                            .thumbs_view
+                            .update_from_collection(coll, &state.filter);
+                    }
+                }
+            };
+            if let Err(e) = result {
+                egui_state
+                    .modal
+                    .err(format!("Sequence operation failed: {e}"));
+            }
+        }
         open
     });
+    egui_state.sequence_windows.extend(new_windows);
 }
 
 pub(super) fn do_sequences_window(
@@ -231,6 +475,7 @@ pub(super) fn do_sequences_window(
                 ScrollArea::vertical().show(ui, |ui| {
                     coll.sequences.retain(|&uid, seq| {
                         let mut retain = true;
+                        let mut open_at = None;
                         if !seq
                             .name
                             .to_lowercase()
@@ -248,6 +493,14 @@ pub(super) fn do_sequences_window(
                                     .sequence_windows
                                     .push(SequenceWindow::new(uid, None));
                             }
+                            if let Some(pos) = seq.progress_pos()
+                                && ui
+                                    .button(format!("▶ Continue ({pos}/{})", seq.entries.len()))
+                                    .on_hover_text("Continue reading from the last viewed entry")
+                                    .clicked()
+                            {
+                                open_at = seq.resume_entry();
+                            }
                             let del_butt = Button::new(concat!(icons::REMOVE, " Delete"))
                                 .fill(Color32::from_rgb(130, 14, 14));
                             if ui.add(del_butt).clicked() {
@@ -262,26 +515,30 @@ pub(super) fn do_sequences_window(
                                     (128., 128.),
                                 ));
                                 if ui.add(but).clicked() {
-                                    let result = try {
-                                        if preferences.use_built_in_viewer {
-                                            builtin::open_sequence(state, seq, *en, window)?;
-                                        } else {
-                                            external::open_sequence(
-                                                seq,
-                                                *en,
-                                                &coll.entries,
-                                                preferences,
-                                            )?;
-                                        }
-                                    };
-                                    if let Err(e) = result {
-                                        egui_state
-                                            .modal
-                                            .err(format!("Error opening sequence: {e:?}"));
-                                    }
+                                    open_at = Some(*en);
                                 }
                             }
                         });
+                        if let Some(en) = open_at {
+                            let result = try {
+                                if preferences.use_built_in_viewer {
+                                    builtin::open_sequence(state, uid, seq, en, window)?;
+                                } else {
+                                    external::open_sequence(
+                                        seq,
+                                        en,
+                                        &coll.entries,
+                                        &coll.roots,
+                                        preferences,
+                                    )?;
+                                }
+                            };
+                            if let Err(e) = result {
+                                egui_state
+                                    .modal
+                                    .err(format!("Error opening sequence: {e:?}"));
+                            }
+                        }
                         retain
                     });
                 });
@@ -292,11 +549,20 @@ pub(super) fn do_sequences_window(
 pub struct SequenceWindow {
     uid: sequence::Id,
     focus_req: Option<entry::Id>,
+    /// Sequence picked for merging into this one
+    merge_from: Option<sequence::Id>,
+    /// Name of the tag to add to the sequence
+    tag_input: String,
 }
 
 impl SequenceWindow {
     pub fn new(uid: sequence::Id, focus_req: Option<entry::Id>) -> Self {
-        Self { uid, focus_req }
+        Self {
+            uid,
+            focus_req,
+            merge_from: None,
+            tag_input: String::new(),
+        }
     }
 }
 
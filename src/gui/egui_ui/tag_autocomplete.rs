@@ -104,6 +104,23 @@ pub(super) fn tag_autocomplete_popup(
                 "@f[]",
             ),
             ("@seq", "Part of a sequence", "@seq"),
+            (
+                "@seq[name]",
+                "Part of the sequence with this name (use _ for spaces)",
+                "@seq[]",
+            ),
+            ("@seq-first", "First entry of a sequence", "@seq-first"),
+            ("@seq-last", "Last entry of a sequence", "@seq-last"),
+            (
+                "@seq-unfinished",
+                "Part of a sequence that wasn't read to the end",
+                "@seq-unfinished",
+            ),
+            (
+                "@seqlen[>n]",
+                "Part of a sequence with a matching length (e.g. >20, <=5, 3..10)",
+                "@seqlen[]",
+            ),
             ("@sel[name]", "Part of a selection buffer", "@sel"),
             ("@untagged", "Entries that don't have any tags", "@untagged"),
             (
@@ -111,6 +128,33 @@ pub(super) fn tag_autocomplete_popup(
                 "The entry must have this many tags",
                 "@ntags[]",
             ),
+            (
+                "@rating[>=n]",
+                "Entries with a matching star rating (e.g. >=4, 0, 2..3)",
+                "@rating[]",
+            ),
+            ("@fav", "Favorite entries", "@fav"),
+            (
+                "@prop[key=value]",
+                "Entries with this property value (use _ for spaces)",
+                "@prop[]",
+            ),
+            ("@has[key]", "Entries that have this property", "@has[]"),
+            (
+                "@related[type]",
+                "Entries with a relation (variant, source or related)",
+                "@related",
+            ),
+            (
+                "@note[text]",
+                "Entries with notes containing this text (use _ for spaces)",
+                "@note[]",
+            ),
+            (
+                "@violations",
+                "Entries that break a tag rule",
+                "@violations",
+            ),
         ];
         let last_is_special = last.bytes().next() == Some(b'@');
         if last_is_special {
@@ -1,18 +1,21 @@
 use {
     super::{
         EguiState, icons,
+        modal::ModalDialog,
         tag_autocomplete::{AcState, tag_autocomplete_popup},
+        tag_tree::{self, TreeCounts},
         ui_ext::UiExt,
     },
     crate::{
         collection::{Collection, TagsExt},
         db::{TagSet, UidCounter},
-        dlog,
-        gui::{State, egui_ui::PromptAction},
+        dlog, entry,
+        filter_reqs::Requirements,
+        gui::{State, egui_ui::PromptAction, thumbnails_view::ThumbnailsView},
         tag,
     },
     constcat::concat,
-    egui_sf2g::egui::{Button, Color32, Context, Grid, Key, RichText, ScrollArea, TextEdit},
+    egui_sf2g::egui::{Button, Color32, Context, Grid, Key, RichText, ScrollArea, TextEdit, Ui},
 };
 
 #[derive(Default)]
@@ -26,6 +29,30 @@ pub struct TagWindow {
     new_tag: TextInputPrompt,
     merge_this: Option<tag::Id>,
     ac_state: AcState,
+    /// Show the tags as a hierarchy built from their implications
+    tree_mode: bool,
+    tree_counts: TreeCounts,
+    split: Option<SplitState>,
+}
+
+/// State of the form for splitting a new tag off of an existing one
+struct SplitState {
+    tag: tag::Id,
+    /// Which names of the tag move over to the new tag
+    move_names: Vec<bool>,
+    new_name: String,
+    entries: SplitEntries,
+    /// Filtered entries with the tag, along with the
+    /// `(tags_revision, generation)` they were collected at
+    filtered: Option<((u64, u64), Vec<entry::Id>)>,
+}
+
+/// Which entries get the new tag instead of the old one when splitting
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum SplitEntries {
+    None,
+    Selected,
+    Filtered,
 }
 
 #[derive(Default)]
@@ -88,7 +115,12 @@ pub(super) fn do_frame(
     let modal = &mut egui_state.modal;
     let merge_this = &mut egui_state.tag_window.merge_this;
     let ac_state = &mut egui_state.tag_window.ac_state;
-    let sel = &mut state.sel;
+    let tree_mode = &mut egui_state.tag_window.tree_mode;
+    let tree_counts = &mut egui_state.tag_window.tree_counts;
+    let split = &mut egui_state.tag_window.split;
+    if *tree_mode {
+        tree_counts.refresh(coll, entries_view.generation());
+    }
     // Clear selected uids that have already been deleted
     selected_uids.retain(|uid| coll.tags.contains_key(uid));
     egui_sf2g::egui::Window::new(concat!(icons::TAG, " Tag list"))
@@ -104,9 +136,11 @@ pub(super) fn do_frame(
                 {
                     tag_filter_string.clear();
                 }
+                ui.toggle_value(tree_mode, "🌲 Tree")
+                    .on_hover_text("Show tags under the tags they imply");
                 if ui.button("Clear all filters").clicked() {
                     reqs.clear();
-                    entries_view.update_from_collection(coll, reqs, sel);
+                    entries_view.update_from_collection(coll, reqs);
                 }
                 if new_tag.inactive() {
                     if ui.button("Add new tag").clicked() {
@@ -144,6 +178,24 @@ pub(super) fn do_frame(
                         .max_height(600.0)
                         .auto_shrink([false, false]);
                     scroll.show(ui, |ui| {
+                        if *tree_mode {
+                            match tag_tree::tree_ui(ui, coll, reqs, tree_counts, tag_filter_string)
+                            {
+                                Ok(resp) => {
+                                    if let Some(id) = resp.toggled {
+                                        reqs.toggle_have_tag(id);
+                                        reqs.set_not_have_tag(id, false);
+                                        *filter_string = reqs.to_string(&coll.tags);
+                                        entries_view.update_from_collection(coll, reqs);
+                                    }
+                                    if let Some(id) = resp.activated {
+                                        *active = Some(id);
+                                    }
+                                }
+                                Err(e) => modal.err(format!("{e:?}")),
+                            }
+                            return;
+                        }
                         Grid::new("tag_window_grid")
                             .spacing((8.0, 8.0))
                             .striped(true)
@@ -232,7 +284,7 @@ pub(super) fn do_frame(
                                     ui.end_row();
                                     if clicked_any {
                                         *filter_string = reqs.to_string(&coll.tags);
-                                        entries_view.update_from_collection(coll, reqs, sel);
+                                        entries_view.update_from_collection(coll, reqs);
                                     }
                                 }
                             });
@@ -397,6 +449,7 @@ pub(super) fn do_frame(
                                                 return;
                                             };
                                             tag.implies.insert(resolved_id);
+                                            coll.tags_changed();
                                         } else {
                                             modal.err(format!("No such tag: {imply:?}"));
                                         }
@@ -422,6 +475,25 @@ pub(super) fn do_frame(
                                     return;
                                 };
                                 tag.implies.remove(&imply_id);
+                                coll.tags_changed();
+                            }
+                            ui.add_space(12.0);
+                            ui.label("Implied by");
+                            ui.add_space(4.0);
+                            let mut implied_by: Vec<tag::Id> = coll
+                                .tags
+                                .iter()
+                                .filter(|(_, tag)| tag.implies.contains(id))
+                                .map(|(implier_id, _)| *implier_id)
+                                .collect();
+                            implied_by.sort_by_key(|uid| coll.tags.first_name_of(uid));
+                            if implied_by.is_empty() {
+                                ui.weak("(No tags imply this tag)");
+                            }
+                            for implier_id in &implied_by {
+                                if ui.link(coll.tags.first_name_of(implier_id)).clicked() {
+                                    sel = Some(*implier_id);
+                                }
                             }
                             ui.separator();
                             ui.heading("Actions");
@@ -440,6 +512,33 @@ pub(super) fn do_frame(
                                     }
                                 }
                             }
+                            match split {
+                                Some(sp) if sp.tag == *id => {
+                                    ui.separator();
+                                    if split_ui(
+                                        ui,
+                                        sp,
+                                        coll,
+                                        reqs,
+                                        entries_view,
+                                        uid_counter,
+                                        modal,
+                                    ) {
+                                        *split = None;
+                                    }
+                                }
+                                _ => {
+                                    if ui.button("Split tag...").clicked() {
+                                        *split = Some(SplitState {
+                                            tag: *id,
+                                            move_names: Vec::new(),
+                                            new_name: String::new(),
+                                            entries: SplitEntries::None,
+                                            filtered: None,
+                                        });
+                                    }
+                                }
+                            }
                             if let Some(sel) = sel {
                                 *active = Some(sel);
                             }
@@ -457,3 +556,93 @@ pub(super) fn do_frame(
         egui_state.tag_window.on = false;
     }
 }
+
+/// Returns whether the split form should be closed
+fn split_ui(
+    ui: &mut Ui,
+    sp: &mut SplitState,
+    coll: &mut Collection,
+    reqs: &Requirements,
+    entries_view: &mut ThumbnailsView,
+    uid_counter: &mut UidCounter,
+    modal: &mut ModalDialog,
+) -> bool {
+    let Some(names) = coll.tags.get(&sp.tag).map(|tag| tag.names.clone()) else {
+        return true;
+    };
+    ui.heading("Split tag");
+    ui.label("Names to move to the new tag");
+    sp.move_names.resize(names.len(), false);
+    for (name, move_name) in names.iter().zip(&mut sp.move_names) {
+        ui.checkbox(move_name, name);
+    }
+    ui.add(TextEdit::singleline(&mut sp.new_name).hint_text("Additional name for the new tag"));
+    let has_tag = |id: &entry::Id| {
+        coll.entries
+            .get(id)
+            .is_some_and(|en| en.tags.contains(&sp.tag))
+    };
+    let selected: Vec<entry::Id> = coll
+        .sel_bufs
+        .selected_ids_iter()
+        .filter(|id| has_tag(id))
+        .copied()
+        .collect();
+    // Filtering the whole collection every frame is too slow, so only redo it on changes
+    let made_at = (coll.tags_revision(), entries_view.generation());
+    if sp.filtered.as_ref().is_none_or(|(at, _)| *at != made_at) {
+        sp.filtered = Some((made_at, coll.filter(reqs).filter(has_tag).collect()));
+    }
+    let filtered = sp
+        .filtered
+        .as_ref()
+        .map_or(&[][..], |(_, ids)| ids.as_slice());
+    ui.label("Entries that get the new tag instead");
+    ui.radio_value(&mut sp.entries, SplitEntries::None, "None");
+    ui.radio_value(
+        &mut sp.entries,
+        SplitEntries::Selected,
+        format!("Selected entries with this tag ({})", selected.len()),
+    );
+    ui.radio_value(
+        &mut sp.entries,
+        SplitEntries::Filtered,
+        format!("Filtered entries with this tag ({})", filtered.len()),
+    );
+    let mut close = false;
+    ui.horizontal(|ui| {
+        if ui.button(concat!(icons::CHECK, " Split")).clicked() {
+            let mut new_names: Vec<String> = names
+                .iter()
+                .zip(&sp.move_names)
+                .filter(|(_, move_name)| **move_name)
+                .map(|(name, _)| name.clone())
+                .collect();
+            let new_name = sp.new_name.trim().to_ascii_lowercase();
+            if !new_name.is_empty() {
+                new_names.push(new_name);
+            }
+            let entries = match sp.entries {
+                SplitEntries::None => &[][..],
+                SplitEntries::Selected => &selected[..],
+                SplitEntries::Filtered => filtered,
+            };
+            match coll.split_tag(sp.tag, new_names, entries, uid_counter) {
+                Ok(new) => {
+                    modal.success(format!(
+                        "Split off {} from {}",
+                        coll.tags.first_name_of(&new),
+                        coll.tags.first_name_of(&sp.tag)
+                    ));
+                    entries_view.update_from_collection(coll, reqs);
+                    close = true;
+                }
+                Err(e) => modal.err(format!("Failed to split tag: {e}")),
+            }
+        }
+        if ui.button(icons::CANCEL_TEXT).clicked() {
+            close = true;
+        }
+    });
+    close
+}
@@ -0,0 +1,302 @@
+use {
+    super::{EguiState, PromptAction, icons},
+    crate::{
+        collection::{Collection, TagsExt},
+        entry, tag,
+        tag_stats::{self, SimilarPair, TagUsage},
+    },
+    egui_sf2g::egui::{self, Context, Grid, ScrollArea},
+    fnv::FnvHashSet,
+};
+
+pub struct TagStatsWindow {
+    pub open: bool,
+    tab: Tab,
+    report: Option<Report>,
+    /// Maximum edit distance for names to count as similar
+    max_distance: usize,
+    /// Similar pairs checked for bulk merging
+    checked: FnvHashSet<(tag::Id, tag::Id)>,
+}
+
+impl Default for TagStatsWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            tab: Tab::default(),
+            report: None,
+            max_distance: 1,
+            checked: FnvHashSet::default(),
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Eq, Clone, Copy)]
+enum Tab {
+    #[default]
+    Usage,
+    Unused,
+    UsedOnce,
+    Similar,
+}
+
+struct Report {
+    /// Every tag along with its usage, most used first
+    usage: Vec<(tag::Id, TagUsage)>,
+    /// Tags used directly by a single entry, along with that entry
+    used_once: Vec<(tag::Id, entry::Id)>,
+    similar: Vec<SimilarPair>,
+    /// Number of tags when the report was made. Deleting or merging tags changes this.
+    n_tags: usize,
+}
+
+impl Report {
+    fn new(coll: &Collection, max_distance: usize) -> Self {
+        let mut usage: Vec<_> = tag_stats::usage_counts(coll).into_iter().collect();
+        usage.sort_by(|(a_id, a), (b_id, b)| {
+            b.total().cmp(&a.total()).then_with(|| {
+                coll.tags
+                    .first_name_of(a_id)
+                    .cmp(&coll.tags.first_name_of(b_id))
+            })
+        });
+        let once: FnvHashSet<tag::Id> = usage
+            .iter()
+            .filter(|(_, usage)| usage.direct == 1)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut used_once = Vec::new();
+        for (en_id, en) in &coll.entries {
+            for tag_id in en.tags.iter().filter(|id| once.contains(id)) {
+                used_once.push((*tag_id, *en_id));
+            }
+        }
+        used_once
+            .sort_by(|(a, _), (b, _)| coll.tags.first_name_of(a).cmp(&coll.tags.first_name_of(b)));
+        Self {
+            usage,
+            used_once,
+            similar: tag_stats::similar_names(&coll.tags, max_distance),
+            n_tags: coll.tags.len(),
+        }
+    }
+    fn usage_of(&self, id: tag::Id) -> TagUsage {
+        self.usage
+            .iter()
+            .find_map(|(tag_id, usage)| (*tag_id == id).then_some(*usage))
+            .unwrap_or_default()
+    }
+    /// The tag of the pair that should be merged into the other, which is the less used one
+    fn merge_direction(&self, pair: &SimilarPair) -> (tag::Id, tag::Id) {
+        if self.usage_of(pair.a).total() < self.usage_of(pair.b).total() {
+            (pair.a, pair.b)
+        } else {
+            (pair.b, pair.a)
+        }
+    }
+}
+
+pub(super) fn do_frame(egui_state: &mut EguiState, coll: &Collection, egui_ctx: &Context) {
+    let win = &mut egui_state.tag_stats_window;
+    if !win.open {
+        return;
+    }
+    if win
+        .report
+        .as_ref()
+        .is_none_or(|report| report.n_tags != coll.tags.len())
+    {
+        win.report = Some(Report::new(coll, win.max_distance));
+        win.checked.clear();
+    }
+    let modal = &mut egui_state.modal;
+    let tag_window = &mut egui_state.tag_window;
+    let mut open_entry = None;
+    let mut refresh = false;
+    egui::Window::new("📊 Tag statistics")
+        .open(&mut win.open)
+        .show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut win.tab, Tab::Usage, "Usage");
+                ui.selectable_value(&mut win.tab, Tab::Unused, "Unused");
+                ui.selectable_value(&mut win.tab, Tab::UsedOnce, "Used once");
+                ui.selectable_value(&mut win.tab, Tab::Similar, "Similar names");
+                if ui.button("♻ Refresh").clicked() {
+                    refresh = true;
+                }
+            });
+            ui.separator();
+            let Some(report) = &win.report else {
+                return;
+            };
+            let mut tag_link = |ui: &mut egui::Ui, id: &tag::Id| {
+                if ui.link(coll.tags.first_name_of(id)).clicked() {
+                    tag_window.on = true;
+                    tag_window.prop_active = Some(*id);
+                }
+            };
+            match win.tab {
+                Tab::Usage => {
+                    ScrollArea::vertical().max_height(600.0).show(ui, |ui| {
+                        Grid::new("tag_stats_usage_grid")
+                            .striped(true)
+                            .num_columns(4)
+                            .show(ui, |ui| {
+                                ui.strong("Tag");
+                                ui.strong("Direct");
+                                ui.strong("Implied");
+                                ui.strong("Total");
+                                ui.end_row();
+                                for (id, usage) in &report.usage {
+                                    tag_link(ui, id);
+                                    ui.label(usage.direct.to_string());
+                                    ui.label(usage.implied.to_string());
+                                    ui.label(usage.total().to_string());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                }
+                Tab::Unused => {
+                    let unused: Vec<tag::Id> = report
+                        .usage
+                        .iter()
+                        .filter(|(_, usage)| usage.total() == 0)
+                        .map(|(id, _)| *id)
+                        .collect();
+                    if unused.is_empty() {
+                        ui.label("Every tag is in use");
+                        return;
+                    }
+                    if ui
+                        .button(format!(
+                            "{} Delete all {} unused tags",
+                            icons::REMOVE,
+                            unused.len()
+                        ))
+                        .clicked()
+                    {
+                        modal.prompt(
+                            "Tag deletion",
+                            format!("Delete {} unused tags?", unused.len()),
+                            PromptAction::DeleteTags(unused.clone()),
+                        );
+                    }
+                    ui.separator();
+                    ScrollArea::vertical().max_height(600.0).show(ui, |ui| {
+                        for id in &unused {
+                            tag_link(ui, id);
+                        }
+                    });
+                }
+                Tab::UsedOnce => {
+                    if report.used_once.is_empty() {
+                        ui.label("No tags are used by just one entry");
+                        return;
+                    }
+                    ui.label("Tags that only one entry has. These are often typos.");
+                    ui.separator();
+                    ScrollArea::vertical().max_height(600.0).show(ui, |ui| {
+                        Grid::new("tag_stats_once_grid")
+                            .striped(true)
+                            .num_columns(2)
+                            .show(ui, |ui| {
+                                for (tag_id, en_id) in &report.used_once {
+                                    tag_link(ui, tag_id);
+                                    let path =
+                                        coll.entries.get(en_id).map_or("<dangling>".into(), |en| {
+                                            en.path.to_string_lossy()
+                                        });
+                                    if ui.link(path).clicked() {
+                                        open_entry = Some(*en_id);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                }
+                Tab::Similar => {
+                    ui.horizontal(|ui| {
+                        ui.label("Max edit distance");
+                        if ui
+                            .add(egui::DragValue::new(&mut win.max_distance).range(1..=4))
+                            .changed()
+                        {
+                            refresh = true;
+                        }
+                    });
+                    if report.similar.is_empty() {
+                        ui.label("No similar tag names found");
+                        return;
+                    }
+                    let merges: Vec<_> = report
+                        .similar
+                        .iter()
+                        .filter(|pair| win.checked.contains(&(pair.a, pair.b)))
+                        .map(|pair| report.merge_direction(pair))
+                        .collect();
+                    if ui
+                        .add_enabled(
+                            !merges.is_empty(),
+                            egui::Button::new(format!("Merge {} checked pairs", merges.len())),
+                        )
+                        .on_hover_text("Each less used tag is merged into the more used one")
+                        .clicked()
+                    {
+                        modal.prompt(
+                            "Tag merge",
+                            format!("Merge {} pairs of tags?", merges.len()),
+                            PromptAction::MergeTags(merges),
+                        );
+                    }
+                    ui.separator();
+                    ScrollArea::vertical().max_height(600.0).show(ui, |ui| {
+                        Grid::new("tag_stats_similar_grid")
+                            .striped(true)
+                            .num_columns(5)
+                            .show(ui, |ui| {
+                                for pair in &report.similar {
+                                    let key = (pair.a, pair.b);
+                                    let mut checked = win.checked.contains(&key);
+                                    if ui.checkbox(&mut checked, "").changed() {
+                                        if checked {
+                                            win.checked.insert(key);
+                                        } else {
+                                            win.checked.remove(&key);
+                                        }
+                                    }
+                                    let (merge, into) = report.merge_direction(pair);
+                                    tag_link(ui, &merge);
+                                    ui.label("→");
+                                    tag_link(ui, &into);
+                                    if ui
+                                        .button("Merge")
+                                        .on_hover_text(format!(
+                                            "{} uses, distance {}",
+                                            report.usage_of(merge).total(),
+                                            pair.distance
+                                        ))
+                                        .clicked()
+                                    {
+                                        let merge_name = coll.tags.first_name_of(&merge);
+                                        let into_name = coll.tags.first_name_of(&into);
+                                        modal.prompt(
+                                            "Tag merge",
+                                            format!("Merge {merge_name} into {into_name}?"),
+                                            PromptAction::MergeTag { merge, into },
+                                        );
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                }
+            }
+        });
+    if refresh {
+        win.report = None;
+    }
+    if let Some(id) = open_entry {
+        egui_state.add_entries_window(vec![id]);
+    }
+}
@@ -0,0 +1,300 @@
+//! Outline of the tag hierarchy defined by [`Tag::implies`](crate::tag::Tag::implies)
+
+use {
+    crate::{
+        collection::{Collection, TagsExt},
+        filter_reqs::Requirements,
+        tag,
+        tag_stats::{self, TagUsage},
+    },
+    anyhow::{Context as _, bail},
+    egui_sf2g::egui::{
+        self, Color32, Label, RichText, Sense, StrokeKind, Ui, collapsing_header::CollapsingState,
+    },
+    fnv::{FnvHashMap, FnvHashSet},
+};
+
+/// Entry counts of the tags, recomputed when the collection changes
+#[derive(Default)]
+pub(super) struct TreeCounts {
+    /// [`Collection::tags_revision`] and
+    /// [`ThumbnailsView::generation`](crate::gui::thumbnails_view::ThumbnailsView::generation)
+    /// the counts were made at
+    made_at: Option<(u64, u64)>,
+    counts: FnvHashMap<tag::Id, TagUsage>,
+}
+
+impl TreeCounts {
+    /// Recompute the counts if the tags changed since the last time.
+    ///
+    /// Tag edits that don't go through [`Collection`] are followed by an update of the
+    /// thumbnails view, so its `generation` covers those.
+    pub(super) fn refresh(&mut self, coll: &Collection, generation: u64) {
+        let key = (coll.tags_revision(), generation);
+        if self.made_at != Some(key) {
+            self.counts = tag_stats::usage_counts(coll);
+            self.made_at = Some(key);
+        }
+    }
+}
+
+/// Drag and drop payload for moving a tag under another one
+#[derive(Clone, Copy)]
+struct TreeDrag {
+    tag: tag::Id,
+    /// The parent the tag was dragged from, if any
+    from_parent: Option<tag::Id>,
+}
+
+struct Reparent {
+    drag: TreeDrag,
+    /// The new parent. `None` moves the tag to the top level.
+    to: Option<tag::Id>,
+    /// Add `to` as an additional parent instead of moving the tag away from its old one
+    keep_old: bool,
+}
+
+#[derive(Default)]
+pub(super) struct TreeResponse {
+    /// Tag that was clicked, for toggling it in the filter
+    pub toggled: Option<tag::Id>,
+    /// Tag to open in the properties panel
+    pub activated: Option<tag::Id>,
+}
+
+struct Tree<'a> {
+    coll: &'a Collection,
+    reqs: &'a Requirements,
+    counts: &'a FnvHashMap<tag::Id, TagUsage>,
+    /// The tags that imply a tag, sorted by name
+    children: FnvHashMap<tag::Id, Vec<tag::Id>>,
+    resp: TreeResponse,
+    reparent: Option<Reparent>,
+}
+
+pub(super) fn tree_ui(
+    ui: &mut Ui,
+    coll: &mut Collection,
+    reqs: &Requirements,
+    counts: &TreeCounts,
+    name_filter: &str,
+) -> anyhow::Result<TreeResponse> {
+    let name_filter = &name_filter.to_lowercase();
+    let by_name =
+        |a: &tag::Id, b: &tag::Id| coll.tags.first_name_of(a).cmp(&coll.tags.first_name_of(b));
+    let mut children: FnvHashMap<tag::Id, Vec<tag::Id>> = FnvHashMap::default();
+    let mut roots = Vec::new();
+    for (id, tag) in &coll.tags {
+        let mut has_parent = false;
+        for parent in tag.implies.iter().filter(|id| coll.tags.contains_key(id)) {
+            children.entry(*parent).or_default().push(*id);
+            has_parent = true;
+        }
+        if !has_parent {
+            roots.push(*id);
+        }
+    }
+    for kids in children.values_mut() {
+        kids.sort_by(by_name);
+    }
+    roots.sort_by(by_name);
+    // Tags that only imply each other in a cycle aren't reachable from any root, so show them
+    // at the top level too
+    let mut reachable = FnvHashSet::default();
+    let mut stack = roots.clone();
+    while let Some(id) = stack.pop() {
+        if reachable.insert(id)
+            && let Some(kids) = children.get(&id)
+        {
+            stack.extend(kids.iter().copied());
+        }
+    }
+    let mut unreachable: Vec<_> = coll
+        .tags
+        .keys()
+        .filter(|id| !reachable.contains(id))
+        .copied()
+        .collect();
+    unreachable.sort_by(by_name);
+    roots.extend(unreachable);
+    let mut tree = Tree {
+        coll,
+        reqs,
+        counts: &counts.counts,
+        children,
+        resp: TreeResponse::default(),
+        reparent: None,
+    };
+    let (_, payload) = ui.dnd_drop_zone::<TreeDrag, _>(egui::Frame::default(), |ui| {
+        ui.label("⮉ Drop here to move to the top level");
+    });
+    if let Some(payload) = payload {
+        tree.reparent = Some(Reparent {
+            drag: *payload,
+            to: None,
+            keep_old: false,
+        });
+    }
+    ui.label(
+        RichText::new(
+            "Drag a tag onto another to make it imply that tag. Hold ctrl to keep the old parent.",
+        )
+        .small()
+        .color(Color32::GRAY),
+    );
+    let base_id = ui.make_persistent_id("tag_tree");
+    for root in roots {
+        if !name_filter.is_empty() && !tree.subtree_matches(root, name_filter, &mut Vec::new()) {
+            continue;
+        }
+        tree.node_ui(ui, root, None, &mut Vec::new(), base_id, name_filter);
+    }
+    let Tree { resp, reparent, .. } = tree;
+    if let Some(reparent) = reparent {
+        apply_reparent(coll, &reparent)?;
+    }
+    Ok(resp)
+}
+
+impl Tree<'_> {
+    fn kids_of(&self, id: tag::Id) -> Vec<tag::Id> {
+        self.children.get(&id).cloned().unwrap_or_default()
+    }
+    /// Whether the tag or any of its descendants have a name containing `filter`,
+    /// which is lowercase
+    fn subtree_matches(&self, id: tag::Id, filter: &str, ancestors: &mut Vec<tag::Id>) -> bool {
+        if ancestors.contains(&id) {
+            return false;
+        }
+        if self
+            .coll
+            .tags
+            .first_name_of(&id)
+            .to_lowercase()
+            .contains(filter)
+        {
+            return true;
+        }
+        ancestors.push(id);
+        let matches = self.children.get(&id).is_some_and(|kids| {
+            kids.iter()
+                .any(|kid| self.subtree_matches(*kid, filter, ancestors))
+        });
+        ancestors.pop();
+        matches
+    }
+    fn node_ui(
+        &mut self,
+        ui: &mut Ui,
+        id: tag::Id,
+        parent: Option<tag::Id>,
+        ancestors: &mut Vec<tag::Id>,
+        ui_id: egui::Id,
+        name_filter: &str,
+    ) {
+        let ui_id = ui_id.with(id.0);
+        if ancestors.contains(&id) {
+            ui.label(
+                RichText::new(format!("{} (cycle)", self.coll.tags.first_name_of(&id)))
+                    .color(Color32::RED),
+            );
+            return;
+        }
+        let kids: Vec<_> = self
+            .kids_of(id)
+            .into_iter()
+            .filter(|kid| {
+                name_filter.is_empty()
+                    || self.subtree_matches(*kid, name_filter, &mut ancestors.clone())
+            })
+            .collect();
+        if kids.is_empty() {
+            ui.horizontal(|ui| {
+                ui.add_space(ui.spacing().indent);
+                self.header_ui(ui, id, parent, ui_id);
+            });
+            return;
+        }
+        ancestors.push(id);
+        CollapsingState::load_with_default_open(ui.ctx(), ui_id, !name_filter.is_empty())
+            .show_header(ui, |ui| self.header_ui(ui, id, parent, ui_id))
+            .body(|ui| {
+                for kid in kids {
+                    self.node_ui(ui, kid, Some(id), ancestors, ui_id, name_filter);
+                }
+            });
+        ancestors.pop();
+    }
+    fn header_ui(&mut self, ui: &mut Ui, id: tag::Id, parent: Option<tag::Id>, ui_id: egui::Id) {
+        let name = self.coll.tags.first_name_of(&id);
+        let count = self.counts.get(&id).map_or(0, |usage| usage.total());
+        let mut text = RichText::new(format!("{name} ({count})"));
+        if self.reqs.have_tag(id) {
+            text = text.background_color(Color32::from_rgb(20, 100, 20));
+        } else if self.reqs.not_have_tag(id) {
+            text = text.background_color(Color32::from_rgb(100, 20, 20));
+        }
+        let drag = TreeDrag {
+            tag: id,
+            from_parent: parent,
+        };
+        let re = ui.dnd_drag_source(ui_id.with("drag"), drag, |ui| {
+            ui.add(Label::new(text).sense(Sense::click()))
+        });
+        if re.inner.clicked() {
+            self.resp.toggled = Some(id);
+        }
+        re.inner.context_menu(|ui| {
+            if ui.button("Edit properties").clicked() {
+                self.resp.activated = Some(id);
+            }
+        });
+        if re
+            .response
+            .dnd_hover_payload::<TreeDrag>()
+            .is_some_and(|payload| payload.tag != id)
+        {
+            ui.painter().rect_stroke(
+                re.response.rect,
+                egui::CornerRadius::same(2),
+                (1.0, Color32::YELLOW),
+                StrokeKind::Outside,
+            );
+        }
+        if let Some(payload) = re.response.dnd_release_payload::<TreeDrag>() {
+            self.reparent = Some(Reparent {
+                drag: *payload,
+                to: Some(id),
+                keep_old: ui.input(|inp| inp.modifiers.ctrl),
+            });
+        }
+    }
+}
+
+fn apply_reparent(coll: &mut Collection, reparent: &Reparent) -> anyhow::Result<()> {
+    let Reparent { drag, to, keep_old } = *reparent;
+    if to == drag.from_parent {
+        return Ok(());
+    }
+    if let Some(to) = to
+        && tag_stats::implied_closure([to], &coll.tags).contains(&drag.tag)
+    {
+        bail!(
+            "Can't move {} under {}, because that would make an implication cycle",
+            coll.tags.first_name_of(&drag.tag),
+            coll.tags.first_name_of(&to)
+        );
+    }
+    let tag = coll
+        .tags
+        .get_mut(&drag.tag)
+        .context("Dragged tag no longer exists")?;
+    if !keep_old && let Some(from) = drag.from_parent {
+        tag.implies.remove(&from);
+    }
+    if let Some(to) = to {
+        tag.implies.insert(to);
+    }
+    coll.tags_changed();
+    Ok(())
+}
@@ -2,8 +2,11 @@ use {
     super::{Action, EguiState, FileOp, PromptAction, icons},
     crate::{
         application::Application,
-        collection::{self, SortBy, SortOrder},
-        gui::{Activity, SelectionBuf, State, viewer},
+        cbz,
+        collection::{self, Collection, SortBy, SortOrder, TagsExt},
+        db::TagSet,
+        gui::{Activity, State, tagger, viewer},
+        selection::{SelectionBuf, SelectionBufs, SetOp},
     },
     anyhow::anyhow,
     constcat::concat,
@@ -48,7 +51,10 @@ pub(super) fn do_frame(
     if !egui_state.top_bar.visible {
         return Ok(());
     }
-    let n_selected = state.sel.current_mut().map_or(0, |buf| buf.len());
+    let n_selected = app
+        .active_collection
+        .as_ref()
+        .map_or(0, |(_, coll)| coll.sel_bufs.n_selected());
     let mut result = Ok(());
     egui::Panel::top("top_panel").show_inside(ui, |ui| {
         egui::MenuBar::new().ui(ui, |ui| {
@@ -59,6 +65,11 @@ pub(super) fn do_frame(
                     collection_menu(ui, egui_state);
                 }
                 Activity::Viewer => viewer::menu_ui(ui, state, win),
+                Activity::Tagging => {
+                    if let Some((_, coll)) = &app.active_collection {
+                        tagger::menu_ui(ui, state, coll, win);
+                    }
+                }
             }
             help_menu(ui, app, egui_state);
             ui.separator();
@@ -76,68 +87,13 @@ pub(super) fn do_frame(
                                     .color(Color32::YELLOW),
                             ))
                             .clicked()
-                            && let Some(buf) = state.sel.current_mut()
+                            && let Some((_, coll)) = &mut app.active_collection
                         {
-                            buf.clear();
-                        }
-                    }
-                    let mut i = 0;
-                    state.sel.bufs.retain_mut(|sel| {
-                        let mut retain = true;
-                        if egui_state.top_bar.sel_rename == Some(i) {
-                            let re = ui.text_edit_singleline(&mut sel.name);
-                            if egui_state.top_bar.sel_focus {
-                                re.request_focus();
-                                egui_state.top_bar.sel_focus = false;
-                            }
-                            if re.lost_focus()
-                                && ui.input(|inp| {
-                                    inp.key_pressed(egui::Key::Enter)
-                                        || inp.key_pressed(egui::Key::Escape)
-                                })
-                            {
-                                egui_state.top_bar.sel_rename = None;
-                            }
-                            i += 1;
-                            return true;
-                        }
-                        let mut re = ui.selectable_label(i == state.sel.current, &sel.name);
-                        re = re.on_hover_text(format!("Selection buffer {}", i + 1));
-                        re.context_menu(|ui| {
-                            if ui.button("Remove").clicked() {
-                                retain = false;
-                            }
-                            if ui.button("Rename").clicked() {
-                                egui_state.top_bar.sel_rename = Some(i);
-                                egui_state.top_bar.sel_focus = true;
-                            }
-                        });
-                        if re.clicked() {
-                            state.sel.current = i;
-                        }
-                        if re.double_clicked() {
-                            egui_state.top_bar.sel_rename = Some(i);
-                            egui_state.top_bar.sel_focus = true;
+                            coll.sel_bufs.clear_current();
                         }
-                        i += 1;
-                        retain
-                    });
-                    // Ensure invariants
-                    if state.sel.current >= state.sel.bufs.len() {
-                        state.sel.current = state.sel.bufs.len().saturating_sub(1);
-                    }
-                    if state.sel.bufs.is_empty() {
-                        state.sel.current = 0;
-                        state.sel.bufs.push(SelectionBuf::new("sel-1"));
                     }
-                    if ui
-                        .button(icons::ADD)
-                        .on_hover_text("Add selection buffer")
-                        .clicked()
-                    {
-                        state
-                            .sel
-                            .add_buf(format!("sel-{}", state.sel.bufs.len() + 1));
+                    if let Some((_, coll)) = &mut app.active_collection {
+                        sel_bufs_ui(ui, &mut coll.sel_bufs, egui_state);
                     }
                     if state.select_a.is_some()
                         && ui
@@ -157,7 +113,23 @@ pub(super) fn do_frame(
                         state.viewer_state.index + 1,
                         state.viewer_state.image_list.len()
                     ));
+                    if let Some((_, coll)) = &app.active_collection
+                        && let Some(en) = state
+                            .viewer_state
+                            .shown_entry()
+                            .and_then(|id| coll.entries.get(&id))
+                    {
+                        let stars: String = (1..=5)
+                            .map(|n| if n <= en.rating { '★' } else { '☆' })
+                            .collect();
+                        ui.label(RichText::new(stars).color(Color32::GOLD))
+                            .on_hover_text("0-5: Set rating");
+                        let heart = if en.favorite { "♥" } else { "♡" };
+                        ui.label(RichText::new(heart).color(Color32::RED))
+                            .on_hover_text("F: Toggle favorite");
+                    }
                 }
+                Activity::Tagging => {}
             }
             ui.separator();
             ui.label("(F1 to toggle this panel)");
@@ -185,6 +157,64 @@ pub(super) fn do_frame(
     result
 }
 
+fn sel_bufs_ui(ui: &mut egui::Ui, sel_bufs: &mut SelectionBufs, egui_state: &mut EguiState) {
+    let mut i = 0;
+    sel_bufs.bufs.retain_mut(|sel| {
+        let mut retain = true;
+        if egui_state.top_bar.sel_rename == Some(i) {
+            let re = ui.text_edit_singleline(&mut sel.name);
+            if egui_state.top_bar.sel_focus {
+                re.request_focus();
+                egui_state.top_bar.sel_focus = false;
+            }
+            if re.lost_focus()
+                && ui.input(|inp| {
+                    inp.key_pressed(egui::Key::Enter) || inp.key_pressed(egui::Key::Escape)
+                })
+            {
+                egui_state.top_bar.sel_rename = None;
+            }
+            i += 1;
+            return true;
+        }
+        let mut re = ui.selectable_label(i == sel_bufs.current, &sel.name);
+        re = re.on_hover_text(format!("Selection buffer {}", i + 1));
+        re.context_menu(|ui| {
+            if ui.button("Remove").clicked() {
+                retain = false;
+            }
+            if ui.button("Rename").clicked() {
+                egui_state.top_bar.sel_rename = Some(i);
+                egui_state.top_bar.sel_focus = true;
+            }
+        });
+        if re.clicked() {
+            sel_bufs.current = i;
+        }
+        if re.double_clicked() {
+            egui_state.top_bar.sel_rename = Some(i);
+            egui_state.top_bar.sel_focus = true;
+        }
+        i += 1;
+        retain
+    });
+    // Ensure invariants
+    if sel_bufs.current >= sel_bufs.bufs.len() {
+        sel_bufs.current = sel_bufs.bufs.len().saturating_sub(1);
+    }
+    if sel_bufs.bufs.is_empty() {
+        sel_bufs.current = 0;
+        sel_bufs.bufs.push(SelectionBuf::new("sel-1"));
+    }
+    if ui
+        .button(icons::ADD)
+        .on_hover_text("Add selection buffer")
+        .clicked()
+    {
+        sel_bufs.add_buf(format!("sel-{}", sel_bufs.bufs.len() + 1));
+    }
+}
+
 fn help_menu(ui: &mut egui::Ui, app: &Application, egui_state: &mut EguiState) {
     ui.menu_button("Help", |ui| {
         if ui.button(concat!(icons::QUESTION, "About")).clicked() {
@@ -296,7 +326,6 @@ fn file_menu(
                                 id,
                                 &state.filter,
                                 window_width,
-                                &state.sel,
                             );
                         }
                         Err(e) => {
@@ -361,12 +390,27 @@ fn collection_menu(ui: &mut egui::Ui, egui_state: &mut EguiState) {
         {
             egui_state.tag_window.toggle();
         }
+        if ui.button("📊 Tag statistics").clicked() {
+            egui_state.tag_stats_window.open ^= true;
+        }
+        if ui.button("⇶ Implied tags").clicked() {
+            egui_state.implied_tags_window.open ^= true;
+        }
+        if ui.button("⚖ Tag rules").clicked() {
+            egui_state.rules_window.open ^= true;
+        }
         if ui
             .add(Button::new("⬌ Sequences").shortcut_text("Q"))
             .clicked()
         {
             egui_state.sequences_window.on ^= true;
         }
+        if ui.button("🔢 Detect sequences").clicked() {
+            egui_state.seq_detect_window.open ^= true;
+        }
+        if ui.button("📥 Import from collection").clicked() {
+            egui_state.import_window.open ^= true;
+        }
         if ui.button(concat!(icons::QUESTION, " Changes")).clicked() {
             egui_state.changes_window.open ^= true;
         }
@@ -445,7 +489,19 @@ fn actions_menu(
         {
             egui_state.action = Some(Action::SelectNone);
         }
+        if let Some((_, coll)) = &app.active_collection {
+            ui.menu_button("⛶ Selection buffer operations", |ui| {
+                sel_ops_menu_ui(ui, &coll.sel_bufs, egui_state);
+            });
+        }
         ui.separator();
+        if ui
+            .add_enabled(active_coll, Button::new("⌨ Rapid tagging"))
+            .on_hover_text("Tag the filtered entries one at a time, using tag hotkeys")
+            .clicked()
+        {
+            egui_state.action = Some(Action::StartTagging);
+        }
         if ui
             .add_enabled(
                 n_selected > 0,
@@ -458,17 +514,95 @@ fn actions_menu(
         ui.separator();
         SubMenuButton::new(concat!(icons::SORT, " Sort"))
             .config(MenuConfig::new().close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside))
-            .ui(ui, |ui| sort_menu_ui(active_coll, ui, egui_state, state));
+            .ui(ui, |ui| {
+                let coll = app.active_collection.as_ref().map(|(_, coll)| coll);
+                sort_menu_ui(coll, ui, egui_state, state);
+            });
+        ui.add_enabled_ui(active_coll, |ui| {
+            ui.menu_button("📦 CBZ archives", |ui| {
+                cbz_menu_ui(ui, app, egui_state);
+            });
+        });
+        if ui
+            .add_enabled(
+                active_coll,
+                egui::Checkbox::new(&mut state.thumbs_view.collapse_seqs, "⬌ Collapse sequences"),
+            )
+            .on_hover_text("Show each sequence as a single tile (Shift+Q)")
+            .changed()
+        {
+            egui_state.action = Some(Action::Sort);
+        }
     });
 }
 
+fn cbz_menu_ui(ui: &mut egui::Ui, app: &Application, egui_state: &mut EguiState) {
+    ui.checkbox(&mut egui_state.cbz_comic_info, "Write ComicInfo.xml")
+        .on_hover_text("Title, tags, notes, properties and bookmarks of the exported entries");
+    let selected = app
+        .active_collection
+        .as_ref()
+        .and_then(|(_, coll)| coll.sel_bufs.current_as_nonempty_id_vec());
+    if ui
+        .add_enabled(selected.is_some(), Button::new("Export selection..."))
+        .clicked()
+        && let Some(ids) = selected
+    {
+        egui_state.file_op = Some(FileOp::ExportCbz(cbz::Export::selection(
+            ids.clone(),
+            egui_state.cbz_comic_info,
+        )));
+        egui_state.file_dialog.save_file();
+    }
+    if ui
+        .button("Import archive as sequence...")
+        .on_hover_text("Extract a .cbz or .zip into the collection folder")
+        .clicked()
+    {
+        egui_state.file_op = Some(FileOp::ImportCbz);
+        egui_state.file_dialog.pick_file();
+    }
+}
+
+fn sel_ops_menu_ui(ui: &mut egui::Ui, sel_bufs: &SelectionBufs, egui_state: &mut EguiState) {
+    let current_name = sel_bufs.current().map_or("", |buf| buf.name.as_str());
+    ui.label(format!("Modify \"{current_name}\" with:"));
+    ui.separator();
+    let mut any_others = false;
+    for (idx, name) in sel_bufs.others() {
+        any_others = true;
+        ui.menu_button(name, |ui| {
+            if ui.button("∪ Union").clicked() {
+                egui_state.action = Some(Action::SelectionOp(SetOp::Union(idx)));
+            }
+            if ui.button("∩ Intersection").clicked() {
+                egui_state.action = Some(Action::SelectionOp(SetOp::Intersection(idx)));
+            }
+            if ui.button("∖ Difference").clicked() {
+                egui_state.action = Some(Action::SelectionOp(SetOp::Difference(idx)));
+            }
+        });
+    }
+    if !any_others {
+        ui.label("(No other selection buffers)");
+    }
+    ui.separator();
+    if ui
+        .button("Invert (against filter)")
+        .on_hover_text("Select the filtered entries that aren't selected, and deselect the rest")
+        .clicked()
+    {
+        egui_state.action = Some(Action::SelectionOp(SetOp::Invert));
+    }
+}
+
 fn sort_menu_ui(
-    active_coll: bool,
+    coll: Option<&Collection>,
     ui: &mut egui::Ui,
     egui_state: &mut EguiState,
     state: &mut State,
 ) {
-    if !active_coll {
+    if coll.is_none() {
         ui.disable();
     }
     if ui
@@ -491,6 +625,23 @@ fn sort_menu_ui(
         SortBy::NTags,
         "By number of tags",
     );
+    ui.selectable_value(&mut state.thumbs_view.sort_by, SortBy::Rating, "By rating");
+    if let Some(coll) = coll {
+        let valued: TagSet = coll
+            .entries
+            .values()
+            .flat_map(|en| en.tag_values.keys().copied())
+            .collect();
+        let mut valued: Vec<_> = valued.into_iter().collect();
+        valued.sort_by_cached_key(|id| coll.tags.first_name_of(id));
+        for id in valued {
+            ui.selectable_value(
+                &mut state.thumbs_view.sort_by,
+                SortBy::TagValue(id),
+                format!("By value of {}", coll.tags.first_name_of(&id)),
+            );
+        }
+    }
     ui.separator();
     ui.selectable_value(
         &mut state.thumbs_view.sort_order,
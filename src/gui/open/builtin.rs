@@ -3,16 +3,23 @@ use {
         collection::Collection,
         entry,
         gui::{Activity, State},
-        sequence::Sequence,
+        sequence::{self, Sequence},
     },
     egui_sf2g::sf2g::graphics::RenderWindow,
 };
 
 /// Open functionality when enter is pressed in thumbnails view
-pub(in crate::gui) fn on_enter_open(state: &mut State, window: &RenderWindow) {
-    if state.sel.none_selected() {
-        open_list(state, state.thumbs_view.uids.clone(), 0, window);
-    } else if let Some(id_vec) = state.sel.current_as_nonempty_id_vec() {
+pub(in crate::gui) fn on_enter_open(state: &mut State, coll: &Collection, window: &RenderWindow) {
+    if coll.sel_bufs.none_selected() {
+        // Start at the keyboard cursor, if there is one
+        let start = state
+            .thumbs_view
+            .highlight
+            .map(|idx| idx as usize)
+            .filter(|&idx| idx < state.thumbs_view.uids.len())
+            .unwrap_or(0);
+        open_list(state, state.thumbs_view.uids.clone(), start, window);
+    } else if let Some(id_vec) = coll.sel_bufs.current_as_nonempty_id_vec() {
         open_list(state, id_vec.clone(), 0, window);
     }
 }
@@ -27,6 +34,7 @@ pub(in crate::gui) fn open_list(
     state.activity = Activity::Viewer;
     state.viewer_state.image_list = image_list;
     state.viewer_state.index = starting_index;
+    state.viewer_state.seq = None;
     state.viewer_state.zoom_to_fit(window);
 }
 
@@ -43,11 +51,8 @@ pub(in crate::gui) fn open_single_with_others(
     window: &RenderWindow,
     thumb_index: usize,
 ) -> anyhow::Result<()> {
-    if let Some(seq) = coll.get_first_related_sequence_of(entry_id) {
-        let Some(image_list) = seq.entry_uids_wrapped_from(entry_id) else {
-            anyhow::bail!("Couldn't get wrapped uids");
-        };
-        open_list(state, image_list, 0, window);
+    if let Some((seq_id, seq)) = coll.get_first_related_sequence_of(entry_id) {
+        open_sequence(state, seq_id, seq, entry_id, window)?;
     } else {
         open_list(state, state.thumbs_view.uids.clone(), thumb_index, window);
     };
@@ -56,13 +61,21 @@ pub(in crate::gui) fn open_single_with_others(
 
 pub(in crate::gui) fn open_sequence(
     state: &mut State,
+    seq_id: sequence::Id,
     seq: &Sequence,
     start_uid: entry::Id,
     window: &RenderWindow,
 ) -> anyhow::Result<()> {
+    if !seq.contains_entry(start_uid) {
+        anyhow::bail!("The entry to start from is not part of the sequence");
+    }
     let Some(uids) = seq.entry_uids_wrapped_from(start_uid) else {
         anyhow::bail!("Couldn't get wrapped uids");
     };
+    if uids.is_empty() {
+        anyhow::bail!("The sequence has no entries");
+    }
     open_list(state, uids, 0, window);
+    state.viewer_state.seq = Some(seq_id);
     Ok(())
 }
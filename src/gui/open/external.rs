@@ -1,13 +1,15 @@
 use {
     crate::{
+        archive,
         collection::{Collection, Entries},
         entry,
         gui::State,
         preferences::{AppId, Preferences},
+        root::{self, Root},
         sequence::Sequence,
     },
     anyhow::{Context, bail},
-    std::{path::Path, process::Command},
+    std::{borrow::Cow, path::Path, process::Command},
 };
 
 /// Open functionality when enter is pressed in thumbnails view
@@ -17,21 +19,21 @@ pub(in crate::gui) fn on_enter_open(
     preferences: &mut Preferences,
 ) -> anyhow::Result<()> {
     let mut candidates: Vec<OpenExternCandidate> = Vec::new();
-    for &uid in state.sel.selected_ids_iter() {
+    for &uid in coll.sel_bufs.selected_ids_iter() {
         candidates.push(OpenExternCandidate {
-            path: &coll.entries[&uid].path,
+            path: coll.entry_path(&coll.entries[&uid]),
             open_with: None,
         });
     }
     if candidates.is_empty() && !state.filter.is_empty() {
-        for uid in coll.filter(&state.filter, &state.sel) {
+        for uid in coll.filter(&state.filter) {
             candidates.push(OpenExternCandidate {
-                path: &coll.entries[&uid].path,
+                path: coll.entry_path(&coll.entries[&uid]),
                 open_with: None,
             });
         }
     }
-    candidates.sort_by_key(|c| c.path);
+    candidates.sort_by(|a, b| a.path.cmp(&b.path));
     open(&candidates, preferences)
 }
 
@@ -42,12 +44,12 @@ pub fn open_single_with_others(
 ) -> anyhow::Result<()> {
     if let Some(seq_id) = coll.find_related_sequences(&[uid]).pop() {
         let seq = &coll.sequences[&seq_id];
-        open_sequence(seq, uid, &coll.entries, preferences)
+        open_sequence(seq, uid, &coll.entries, &coll.roots, preferences)
     } else {
         let en = &coll.entries[&uid];
         open(
             &[OpenExternCandidate {
-                path: &en.path,
+                path: coll.entry_path(en),
                 open_with: find_open_with_for_entry(en, coll),
             }],
             preferences,
@@ -57,15 +59,27 @@ pub fn open_single_with_others(
 
 /// Candidate for opening with extern app
 pub struct OpenExternCandidate<'a> {
-    pub path: &'a Path,
+    pub path: Cow<'a, Path>,
     pub open_with: Option<AppId>,
 }
 
+/// Open `candidates` with external programs.
+///
+/// Files inside archives only exist for cowbump, so they are skipped with an error.
 pub fn open(
     candidates: &[OpenExternCandidate],
     preferences: &mut Preferences,
 ) -> anyhow::Result<()> {
-    let built_tasks = build_tasks(candidates, preferences);
+    let n_virtual = candidates
+        .iter()
+        .filter(|candidate| archive::is_virtual(&candidate.path))
+        .count();
+    let built_tasks = build_tasks(
+        candidates
+            .iter()
+            .filter(|candidate| !archive::is_virtual(&candidate.path)),
+        preferences,
+    );
     for task in built_tasks.tasks {
         let app = &preferences.applications[&task.app];
         let mut cmd = Command::new(&app.path);
@@ -82,6 +96,12 @@ pub fn open(
     for path in built_tasks.remainder {
         open::that(path)?;
     }
+    if n_virtual > 0 {
+        bail!(
+            "Skipped {n_virtual} file(s) inside archives, which external programs can't open.\n\
+             Use the built-in viewer for those."
+        );
+    }
     Ok(())
 }
 
@@ -103,8 +123,8 @@ struct BuiltTasks<'p> {
 /// Panics if one of the paths has invalid UTF-8.
 /// There is not much that can be done about this as serde doesn't support
 /// serializing Non-UTF8 paths.
-fn build_tasks<'p>(
-    candidates: &[OpenExternCandidate<'p>],
+fn build_tasks<'p, 'c: 'p>(
+    candidates: impl Iterator<Item = &'p OpenExternCandidate<'c>>,
     preferences: &mut Preferences,
 ) -> BuiltTasks<'p> {
     let mut tasks: Vec<Task> = Vec::new();
@@ -114,7 +134,7 @@ fn build_tasks<'p>(
         if let Some(app_id) = candidate.open_with {
             tasks.push(Task {
                 app: app_id,
-                args: vec![candidate.path],
+                args: vec![&*candidate.path],
             });
             continue;
         }
@@ -126,11 +146,11 @@ fn build_tasks<'p>(
         match preferences.associations.get(&ext) {
             Some(Some(app_id)) => {
                 if let Some(task) = tasks.iter_mut().find(|task| task.app == *app_id) {
-                    task.args.push(candidate.path);
+                    task.args.push(&*candidate.path);
                 } else {
                     tasks.push(Task {
                         app: *app_id,
-                        args: vec![candidate.path],
+                        args: vec![&*candidate.path],
                     });
                 }
             }
@@ -138,7 +158,7 @@ fn build_tasks<'p>(
                 // Make sure extension preference exists, so the user doesn't
                 // have to add it manually to the list.
                 preferences.associations.insert(ext, None);
-                remainder.push(candidate.path);
+                remainder.push(&*candidate.path);
             }
         }
     }
@@ -155,6 +175,7 @@ pub(crate) fn open_sequence(
     seq: &Sequence,
     start_uid: entry::Id,
     entries: &Entries,
+    roots: &[Root],
     prefs: &mut Preferences,
 ) -> anyhow::Result<()> {
     let mut candidates = Vec::new();
@@ -163,7 +184,7 @@ pub(crate) fn open_sequence(
     };
     for img_uid in uids {
         candidates.push(OpenExternCandidate {
-            path: entries[&img_uid].path.as_ref(),
+            path: root::resolve(roots, entries[&img_uid].root, &entries[&img_uid].path),
             open_with: None,
         });
     }
@@ -0,0 +1,350 @@
+use {
+    super::{Activity, State, viewer},
+    crate::{
+        collection::{AddTagError, Collection, TagsExt},
+        entry,
+        filter_reqs::Requirements,
+        tag,
+    },
+    anyhow::anyhow,
+    egui_sf2g::{
+        egui::{self, Color32, RichText},
+        sf2g::{
+            graphics::{RenderTarget, RenderWindow},
+            window::{Event, Key},
+        },
+    },
+};
+
+/// State of the rapid tagging activity
+pub struct TaggerState {
+    /// Filter that entries have to satisfy to be in the queue
+    pub queue_string: String,
+    queue_reqs: Requirements,
+    /// Entries to tag, in the order of the thumbnails view
+    queue: Vec<entry::Id>,
+    /// Index into `queue`. Equal to `queue.len()` when the queue is finished.
+    index: usize,
+    err_string: String,
+    new_hotkey: String,
+    new_hotkey_tag: String,
+}
+
+impl Default for TaggerState {
+    fn default() -> Self {
+        Self {
+            queue_string: "@untagged".into(),
+            queue_reqs: Requirements::default(),
+            queue: Vec::new(),
+            index: 0,
+            err_string: String::new(),
+            new_hotkey: String::new(),
+            new_hotkey_tag: String::new(),
+        }
+    }
+}
+
+impl TaggerState {
+    pub(in crate::gui) fn current(&self) -> Option<entry::Id> {
+        self.queue.get(self.index).copied()
+    }
+    /// Go to the next entry that still satisfies the queue filter
+    fn advance(&mut self, coll: &Collection) {
+        self.index = (self.index + 1..self.queue.len())
+            .find(|&i| satisfies(coll, self.queue[i], &self.queue_reqs))
+            .unwrap_or(self.queue.len());
+    }
+    fn next(&mut self) {
+        self.index = (self.index + 1).min(self.queue.len().saturating_sub(1));
+    }
+    fn prev(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+}
+
+fn satisfies(coll: &Collection, id: entry::Id, reqs: &Requirements) -> bool {
+    coll.entries.get(&id).is_some_and(|en| {
+        let inherited: Vec<tag::Id> = coll.inherited_tags(id).map(|(tag, _)| tag).collect();
+        en.all_reqs_satisfied(id, reqs, coll, &inherited)
+    })
+}
+
+/// Start rapid tagging the entries of the thumbnails view that satisfy the queue filter
+pub(super) fn start(
+    state: &mut State,
+    coll: &Collection,
+    window: &RenderWindow,
+) -> anyhow::Result<()> {
+    let tagger = &mut state.tagger;
+    tagger
+        .queue_reqs
+        .parse_and_resolve(&tagger.queue_string, coll)
+        .map_err(|e| anyhow!("Invalid queue filter: {e}"))?;
+    tagger.queue = state
+        .thumbs_view
+        .uids
+        .iter()
+        .copied()
+        .filter(|&id| satisfies(coll, id, &tagger.queue_reqs))
+        .collect();
+    if tagger.queue.is_empty() {
+        anyhow::bail!("No entries in view match the queue filter");
+    }
+    tagger.index = 0;
+    tagger.err_string.clear();
+    state.activity = Activity::Tagging;
+    refit(state, window);
+    Ok(())
+}
+
+fn leave(state: &mut State, coll: &Collection) {
+    state.activity = Activity::Thumbnails;
+    // Tags have likely changed, which can change the filter results
+    state
+        .thumbs_view
+        .update_from_collection(coll, &state.filter);
+}
+
+fn refit(state: &mut State, window: &RenderWindow) {
+    if let Some(id) = state.tagger.current() {
+        state.viewer_state.zoom_to_fit_entry(id, window);
+    }
+}
+
+/// Add `tag` to the entry if it doesn't have it, remove it otherwise
+fn toggle_tag(coll: &mut Collection, id: entry::Id, tag: tag::Id) -> Result<(), AddTagError> {
+    match coll.entries.get_mut(&id) {
+        Some(en) if en.tags.contains(&tag) => {
+            en.remove_tag(tag);
+            coll.tags_changed();
+            Ok(())
+        }
+        _ => coll.add_tag_for(id, tag),
+    }
+}
+
+/// The character a tag hotkey is stored as
+fn hotkey_char(code: Key) -> Option<char> {
+    let ch = match code {
+        Key::Num1 => '1',
+        Key::Num2 => '2',
+        Key::Num3 => '3',
+        Key::Num4 => '4',
+        Key::Num5 => '5',
+        Key::Num6 => '6',
+        Key::Num7 => '7',
+        Key::Num8 => '8',
+        Key::Num9 => '9',
+        Key::A => 'a',
+        Key::B => 'b',
+        Key::C => 'c',
+        Key::D => 'd',
+        Key::E => 'e',
+        Key::F => 'f',
+        Key::G => 'g',
+        Key::H => 'h',
+        Key::I => 'i',
+        Key::J => 'j',
+        Key::K => 'k',
+        Key::L => 'l',
+        Key::M => 'm',
+        Key::N => 'n',
+        Key::O => 'o',
+        Key::P => 'p',
+        Key::Q => 'q',
+        Key::R => 'r',
+        Key::S => 's',
+        Key::T => 't',
+        Key::U => 'u',
+        Key::V => 'v',
+        Key::W => 'w',
+        Key::X => 'x',
+        Key::Y => 'y',
+        Key::Z => 'z',
+        _ => return None,
+    };
+    Some(ch)
+}
+
+fn valid_hotkey(ch: char) -> bool {
+    matches!(ch, '1'..='9' | 'a'..='z')
+}
+
+pub(super) fn handle_event(
+    state: &mut State,
+    event: &Event,
+    coll: &mut Collection,
+    window: &RenderWindow,
+) {
+    let Event::KeyPressed {
+        code, ctrl, alt, ..
+    } = *event
+    else {
+        return;
+    };
+    if ctrl || alt {
+        return;
+    }
+    match code {
+        Key::Escape => leave(state, coll),
+        Key::Enter => {
+            state.tagger.advance(coll);
+            refit(state, window);
+        }
+        Key::Left => {
+            state.tagger.prev();
+            refit(state, window);
+        }
+        Key::Right => {
+            state.tagger.next();
+            refit(state, window);
+        }
+        _ => {
+            if let Some(ch) = hotkey_char(code)
+                && let Some(&tag) = coll.tag_hotkeys.get(&ch)
+                && let Some(id) = state.tagger.current()
+                && let Err(e) = toggle_tag(coll, id, tag)
+            {
+                state.tagger.err_string = e.to_string();
+            }
+        }
+    }
+}
+
+pub(super) fn draw(
+    state: &mut State,
+    window: &mut RenderWindow,
+    coll: &Collection,
+    painter: &egui::Painter,
+) {
+    match state.tagger.current() {
+        Some(id) => viewer::draw_entry(&mut state.viewer_state, window, coll, id, painter),
+        None => {
+            let size = window.size();
+            painter.text(
+                egui::pos2(size.x as f32 / 2.0, size.y as f32 / 2.0),
+                egui::Align2::CENTER_CENTER,
+                "Queue finished. Press Esc to go back to the thumbnails.",
+                egui::FontId::proportional(20.0),
+                Color32::WHITE,
+            );
+        }
+    }
+}
+
+pub fn menu_ui(ui: &mut egui::Ui, state: &mut State, coll: &Collection, win: &RenderWindow) {
+    if ui.button("Back (Esc)").clicked() {
+        leave(state, coll);
+    }
+    ui.menu_button("Queue", |ui| {
+        ui.label("Queue filter");
+        let re = ui.text_edit_singleline(&mut state.tagger.queue_string);
+        let enter = re.lost_focus() && ui.input(|inp| inp.key_pressed(egui::Key::Enter));
+        if ui.button("Restart queue").clicked() || enter {
+            if let Err(e) = start(state, coll, win) {
+                state.tagger.err_string = e.to_string();
+            }
+            ui.close();
+        }
+    });
+}
+
+/// Shows the tags of the current entry, the hotkeys, and the progress of the queue
+pub(super) fn overlay_ui(ctx: &egui::Context, state: &mut State, coll: &mut Collection) {
+    let tagger = &mut state.tagger;
+    egui::Window::new("Rapid tagging")
+        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 32.0])
+        .resizable(false)
+        .show(ctx, |ui| {
+            let len = tagger.queue.len();
+            ui.add(
+                egui::ProgressBar::new(tagger.index as f32 / len.max(1) as f32)
+                    .text(format!("{}/{len}", tagger.index)),
+            );
+            let Some(id) = tagger.current() else {
+                ui.label("Queue finished");
+                return;
+            };
+            let Some(en) = coll.entries.get(&id) else {
+                ui.label(RichText::new("Dangling entry").color(Color32::RED));
+                return;
+            };
+            ui.label(en.path.display().to_string());
+            ui.separator();
+            ui.horizontal_wrapped(|ui| {
+                if en.tags.is_empty() {
+                    ui.label("(no tags)");
+                }
+                for tag in &en.tags {
+                    ui.label(RichText::new(coll.tags.first_name_of(tag)).strong());
+                }
+            });
+            ui.separator();
+            let mut toggle = None;
+            let mut unbind = None;
+            for (&ch, tag) in &coll.tag_hotkeys {
+                ui.horizontal(|ui| {
+                    let text = format!("{ch}: {}", coll.tags.first_name_of(tag));
+                    if ui.selectable_label(en.tags.contains(tag), text).clicked() {
+                        toggle = Some(*tag);
+                    }
+                    if ui.small_button("🗑").on_hover_text("Unbind").clicked() {
+                        unbind = Some(ch);
+                    }
+                });
+            }
+            if coll.tag_hotkeys.is_empty() {
+                ui.label("No hotkeys bound yet");
+            }
+            if let Some(tag) = toggle
+                && let Err(e) = toggle_tag(coll, id, tag)
+            {
+                tagger.err_string = e.to_string();
+            }
+            if let Some(ch) = unbind {
+                coll.tag_hotkeys.remove(&ch);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut tagger.new_hotkey)
+                        .hint_text("key")
+                        .char_limit(1)
+                        .desired_width(24.0),
+                );
+                ui.add(
+                    egui::TextEdit::singleline(&mut tagger.new_hotkey_tag)
+                        .hint_text("tag")
+                        .desired_width(120.0),
+                );
+                if ui.button("Bind").clicked() {
+                    match bind_hotkey(coll, &tagger.new_hotkey, &tagger.new_hotkey_tag) {
+                        Ok(()) => {
+                            tagger.new_hotkey.clear();
+                            tagger.new_hotkey_tag.clear();
+                            tagger.err_string.clear();
+                        }
+                        Err(e) => tagger.err_string = e.to_string(),
+                    }
+                }
+            });
+            ui.label("Enter: next in queue, ←/→: browse, Esc: back");
+            if !tagger.err_string.is_empty() {
+                ui.label(RichText::new(&tagger.err_string).color(Color32::RED));
+            }
+        });
+}
+
+fn bind_hotkey(coll: &mut Collection, key: &str, tag_name: &str) -> anyhow::Result<()> {
+    let ch = key
+        .chars()
+        .next()
+        .map(|ch| ch.to_ascii_lowercase())
+        .filter(|&ch| valid_hotkey(ch))
+        .ok_or_else(|| anyhow!("Hotkey must be one of 1-9 or a-z"))?;
+    let tag = coll
+        .resolve_tag(tag_name.trim())
+        .ok_or_else(|| anyhow!("No such tag: {tag_name}"))?;
+    coll.tag_hotkeys.insert(ch, tag);
+    Ok(())
+}
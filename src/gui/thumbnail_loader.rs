@@ -1,6 +1,6 @@
 use {
     super::Thumbnail,
-    crate::{db::EntryMap, dlog, entry, gui::ThumbnailCache},
+    crate::{archive, db::EntryMap, dlog, entry, gui::ThumbnailCache},
     egui_sf2g::sf2g::{cpp::FBox, graphics::Texture},
     image::{ImageBuffer, ImageResult, Rgba, imageops::FilterType},
     parking_lot::Mutex,
@@ -39,7 +39,7 @@ impl ThumbnailLoader {
             let name = name.to_owned();
             let no_ffmpeg = self.no_ffmpeg.clone();
             ::std::thread::spawn(move || {
-                let data = match std::fs::read(&name) {
+                let data = match archive::read(&name) {
                     Ok(data) => data,
                     Err(e) => {
                         slots_clone.lock().insert(
@@ -54,8 +54,10 @@ impl ThumbnailLoader {
                 };
                 let mut image_result = image::load_from_memory(&data);
                 let mut ffmpeg_was_used = false;
+                // ffmpeg can't look into archives
                 if let Err(err) = &image_result
                     && !no_ffmpeg.load(atomic::Ordering::Relaxed)
+                    && !archive::is_virtual(&name)
                 {
                     let result = Command::new("ffmpeg")
                         .args(["-y", "-i"])
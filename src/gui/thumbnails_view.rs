@@ -8,11 +8,14 @@ use {
         thumbnail_loader::ThumbnailLoader,
     },
     crate::{
-        collection::{Collection, Entries, SortBy, SortOrder},
+        archive,
+        collection::{Collection, SortBy, SortOrder},
+        db::{EntryMap, EntrySet},
         dlog, entry,
         filter_reqs::Requirements,
-        gui::SelectionBufs,
         preferences::Preferences,
+        selection::SelectionBufs,
+        sequence,
     },
     anyhow::Context as _,
     egui_sf2g::{
@@ -36,9 +39,51 @@ pub struct ThumbnailsView {
     pub sort_by: SortBy,
     pub sort_order: SortOrder,
     pub uids: Vec<entry::Id>,
+    /// Highlighted entry index. Used by find, and as the keyboard cursor.
     pub highlight: Option<u32>,
+    drag: Option<Drag>,
+    extend: Option<ExtendSel>,
+    /// Show each sequence as a single tile
+    pub collapse_seqs: bool,
+    /// Cover entries of collapsed sequences, along with their sequence
+    covers: EntryMap<sequence::Id>,
+    /// Bumped on every update from the collection
+    generation: u64,
 }
 
+/// A left button press on the grid, which becomes either a click or a rubber band drag
+struct Drag {
+    /// Where the press happened, in absolute (scroll-independent) content coordinates
+    origin: (f32, f32),
+    /// Current pointer position, in absolute content coordinates
+    current: (f32, f32),
+    /// Whether the pointer moved far enough from the origin to count as a drag
+    active: bool,
+    /// The entry that was pressed on, along with its index
+    pressed: Option<(entry::Id, usize)>,
+    /// The selection before the drag started. The drag adds to this.
+    base: Vec<entry::Id>,
+}
+
+/// Shift+arrow selection extension, anchored at the index where it started
+struct ExtendSel {
+    anchor: usize,
+    /// The selection before extending started
+    base: Vec<entry::Id>,
+}
+
+#[derive(Clone, Copy)]
+enum CursorMove {
+    By(isize),
+    First,
+    Last,
+}
+
+/// How far the pointer has to move (in pixels) before a press becomes a drag
+const DRAG_THRESHOLD: f32 = 8.0;
+/// Distance from the top or bottom edge of the window where dragging autoscrolls
+const AUTOSCROLL_EDGE: f32 = 48.0;
+
 fn thumbs_per_row_and_size(window_width: u32, preferences: &Preferences) -> (u8, u32) {
     let thumbnails_per_row = preferences.thumbs_per_row;
     let thumbnail_size = window_width / u32::from(thumbnails_per_row);
@@ -56,6 +101,11 @@ impl ThumbnailsView {
             thumb_size,
             thumbs_per_row,
             highlight: None,
+            drag: None,
+            extend: None,
+            collapse_seqs: false,
+            covers: EntryMap::default(),
+            generation: 0,
         }
     }
     pub fn resize(&mut self, window_width: u32, preferences: &Preferences) {
@@ -66,21 +116,54 @@ impl ThumbnailsView {
         coll: &Collection,
         reqs: &Requirements,
         preferences: &Preferences,
-        sel_bufs: &SelectionBufs,
     ) -> Self {
         let mut this = Self::new(window_width, preferences);
-        this.update_from_collection(coll, reqs, sel_bufs);
+        this.update_from_collection(coll, reqs);
         this
     }
-    pub fn update_from_collection(
-        &mut self,
-        coll: &Collection,
-        reqs: &Requirements,
-        sel_bufs: &SelectionBufs,
-    ) {
-        self.uids = coll.filter(reqs, sel_bufs).collect();
+    pub fn update_from_collection(&mut self, coll: &Collection, reqs: &Requirements) {
+        self.generation += 1;
+        self.uids = coll.filter(reqs).collect();
+        self.extend = None;
+        self.covers.clear();
+        if self.collapse_seqs {
+            self.collapse_sequences(coll);
+        }
         self.sort(coll);
     }
+    /// Replace the entries of sequences with their covers, or another member if the cover
+    /// already stands for a different sequence.
+    ///
+    /// A sequence gets a tile if any of its entries passed the filter.
+    fn collapse_sequences(&mut self, coll: &Collection) {
+        let shown: EntrySet = self.uids.iter().copied().collect();
+        let mut members = EntrySet::default();
+        for (seq_id, seq) in &coll.sequences {
+            members.extend(seq.entries.iter().copied());
+            if !seq.entries.iter().any(|id| shown.contains(id)) {
+                continue;
+            }
+            // An entry can be part of several sequences, so if the cover already stands for
+            // another sequence, fall back to another member
+            let tile = seq
+                .cover()
+                .into_iter()
+                .chain(seq.entries.iter().copied())
+                .find(|id| coll.entries.contains_key(id) && !self.covers.contains_key(id));
+            if let Some(tile) = tile {
+                self.covers.insert(tile, *seq_id);
+            }
+        }
+        self.uids.retain(|id| !members.contains(id));
+        self.uids.extend(self.covers.keys().copied());
+    }
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    /// The sequence `id` stands for, if it's the cover tile of a collapsed sequence
+    pub fn collapsed_seq(&self, id: entry::Id) -> Option<sequence::Id> {
+        self.covers.get(&id).copied()
+    }
     fn sort(&mut self, coll: &Collection) {
         let rev = self.sort_order == SortOrder::Desc;
         match self.sort_by {
@@ -96,6 +179,27 @@ impl ThumbnailsView {
                 let ord = coll.entries[a].tags.len().cmp(&coll.entries[b].tags.len());
                 if rev { ord.reverse() } else { ord }
             }),
+            SortBy::Rating => self.uids.sort_by(|a, b| {
+                let key = |id| {
+                    let en = &coll.entries[id];
+                    (en.rating, en.favorite)
+                };
+                let ord = key(a).cmp(&key(b));
+                if rev { ord.reverse() } else { ord }
+            }),
+            SortBy::TagValue(tag) => self.uids.sort_by(|a, b| {
+                let val = |id| coll.entries[id].tag_values.get(&tag).copied();
+                // Entries without a value stay last in either direction
+                match (val(a), val(b)) {
+                    (Some(a), Some(b)) => {
+                        let ord = a.total_cmp(&b);
+                        if rev { ord.reverse() } else { ord }
+                    }
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                }
+            }),
         }
     }
     pub fn iter(&self) -> impl Iterator<Item = entry::Id> + '_ {
@@ -146,9 +250,6 @@ impl ThumbnailsView {
             self.y_offset = bottom;
         }
     }
-    fn go_to_bottom(&mut self, window: &RenderWindow) {
-        self.y_offset = self.find_bottom(window);
-    }
     /// Returns the absolute thumb index at (x,y) on the screen
     ///
     /// This is absolute, so the top left image on the screen could have a different index
@@ -185,6 +286,109 @@ impl ThumbnailsView {
             *view_y += diff as f32;
         }
     }
+    /// Move the keyboard cursor, and scroll to keep it in view.
+    ///
+    /// If there is no cursor yet, relative moves place it on the first visible entry.
+    /// Returns the new cursor index, or `None` if there are no entries.
+    fn move_cursor(&mut self, mv: CursorMove, window_height: u32) -> Option<usize> {
+        let last = self.uids.len().checked_sub(1)?;
+        let new = match (mv, self.highlight) {
+            (CursorMove::First, _) => 0,
+            (CursorMove::Last, _) => last,
+            (CursorMove::By(delta), Some(idx)) => (idx as usize)
+                .min(last)
+                .saturating_add_signed(delta)
+                .min(last),
+            (CursorMove::By(_), None) => self.skip_take(window_height).0.min(last),
+        };
+        self.highlight = Some(new as u32);
+        self.seek_to_contain_index(new, window_height);
+        Some(new)
+    }
+    /// Indices of the entries that intersect the rubber band rectangle
+    fn indices_in_rect(&self, (x1, y1): (f32, f32), (x2, y2): (f32, f32)) -> Vec<usize> {
+        let thumb_size = self.thumb_size as f32;
+        let max_col = usize::from(self.thumbs_per_row).saturating_sub(1);
+        let col = |x: f32| ((x.max(0.0) / thumb_size) as usize).min(max_col);
+        let row = |y: f32| (y.max(0.0) / thumb_size) as usize;
+        let (c1, c2) = (col(x1.min(x2)), col(x1.max(x2)));
+        let (r1, r2) = (row(y1.min(y2)), row(y1.max(y2)));
+        let mut indices = Vec::new();
+        for r in r1..=r2 {
+            for c in c1..=c2 {
+                let idx = r * usize::from(self.thumbs_per_row) + c;
+                if idx < self.uids.len() {
+                    indices.push(idx);
+                }
+            }
+        }
+        indices
+    }
+    /// Update an ongoing drag: activate it if the pointer moved far enough, autoscroll
+    /// near the window edges, and select everything under the rubber band.
+    pub(super) fn update_drag(&mut self, window: &RenderWindow, sel_bufs: &mut SelectionBufs) {
+        if self.drag.is_none() {
+            return;
+        }
+        // The button could have been released outside of the window
+        if !mouse::Button::Left.is_pressed() {
+            self.drag = None;
+            return;
+        }
+        let mp = window.mouse_position();
+        let (mx, my) = (mp.x as f32, mp.y as f32);
+        let win_h = window.size().y as f32;
+        let active = self.drag.as_ref().is_some_and(|drag| drag.active);
+        if active {
+            let scroll_speed = self.thumb_size as f32 / 8.0;
+            if my < AUTOSCROLL_EDGE {
+                self.y_offset -= scroll_speed * (AUTOSCROLL_EDGE - my) / AUTOSCROLL_EDGE;
+                self.clamp_top();
+            } else if my > win_h - AUTOSCROLL_EDGE {
+                self.y_offset += scroll_speed * (my - (win_h - AUTOSCROLL_EDGE)) / AUTOSCROLL_EDGE;
+                self.clamp_bottom(window);
+            }
+        }
+        let y_offset = self.y_offset;
+        let Some(drag) = &mut self.drag else {
+            return;
+        };
+        drag.current = (mx, my + y_offset);
+        if !drag.active {
+            let (dx, dy) = (
+                drag.current.0 - drag.origin.0,
+                drag.current.1 - drag.origin.1,
+            );
+            if dx.hypot(dy) < DRAG_THRESHOLD {
+                return;
+            }
+            drag.active = true;
+        }
+        let (origin, current) = (drag.origin, drag.current);
+        let base_set: EntrySet = drag.base.iter().copied().collect();
+        let mut new_sel = drag.base.clone();
+        new_sel.extend(
+            self.indices_in_rect(origin, current)
+                .into_iter()
+                .filter_map(|idx| self.get(idx))
+                .filter(|id| !base_set.contains(id)),
+        );
+        if let Some(buf) = sel_bufs.current_mut() {
+            buf.buf = new_sel;
+        }
+    }
+    /// The rubber band rectangle in screen coordinates, if there is an active drag
+    fn rubber_band_rect(&self) -> Option<Rect<f32>> {
+        let drag = self.drag.as_ref().filter(|drag| drag.active)?;
+        let (x1, y1) = drag.origin;
+        let (x2, y2) = drag.current;
+        Some(Rect::new(
+            x1.min(x2),
+            y1.min(y2) - self.y_offset,
+            (x1 - x2).abs(),
+            (y1 - y2).abs(),
+        ))
+    }
     pub fn highlight_and_seek_to_entry(&mut self, id: entry::Id, height: u32) -> bool {
         match self.entry_position(id) {
             Some(idx) => {
@@ -201,7 +405,7 @@ pub(super) fn draw_thumbnails(
     state: &mut State,
     res: &Resources,
     window: &mut RenderWindow,
-    entries: &Entries,
+    coll: &Collection,
     load_anim_rotation: f32,
     pointer_active: bool,
     painter: &egui::Painter,
@@ -227,14 +431,14 @@ pub(super) fn draw_thumbnails(
         let y = (row * thumb_size) as f32 - (state.thumbs_view.y_offset % thumb_size as f32);
         let image_rect = Rect::new(x, y, thumb_size as f32, thumb_size as f32);
         let mouse_over = image_rect.contains(Vector2f::new(mouse_pos.x as f32, mouse_pos.y as f32));
-        if state.sel.current_contains(&uid) {
+        if coll.sel_bufs.current_contains(&uid) {
             sprite.set_color(Color::GREEN);
         } else {
             sprite.set_color(Color::WHITE);
         }
         draw_thumbnail(
             &state.thumbnail_cache,
-            entries,
+            coll,
             window,
             x,
             y,
@@ -246,6 +450,13 @@ pub(super) fn draw_thumbnails(
             load_anim_rotation,
             painter,
         );
+        if let Some(seq) = state
+            .thumbs_view
+            .collapsed_seq(uid)
+            .and_then(|seq_id| coll.sequences.get(&seq_id))
+        {
+            draw_seq_badge(painter, x, y, thumb_size, seq.entries.len());
+        }
         if mouse_over && pointer_active {
             let mut rs = RectangleShape::from_rect(image_rect);
             rs.set_fill_color(Color::rgba(225, 225, 200, 48));
@@ -261,12 +472,19 @@ pub(super) fn draw_thumbnails(
             window.draw_sprite(&s, &RenderStates::DEFAULT);
         }
     }
+    if let Some(rect) = state.thumbs_view.rubber_band_rect() {
+        let mut rs = RectangleShape::from_rect(rect);
+        rs.set_fill_color(Color::rgba(100, 150, 255, 48));
+        rs.set_outline_color(Color::rgb(100, 150, 255));
+        rs.set_outline_thickness(1.0);
+        window.draw_rectangle_shape(&rs, &RenderStates::DEFAULT);
+    }
 }
 
 #[expect(clippy::too_many_arguments)]
 fn draw_thumbnail<'a: 'b, 'b>(
     thumbnail_cache: &'a ThumbnailCache,
-    entries: &Entries,
+    coll: &Collection,
     window: &mut RenderWindow,
     x: f32,
     y: f32,
@@ -278,14 +496,8 @@ fn draw_thumbnail<'a: 'b, 'b>(
     load_anim_rotation: f32,
     painter: &egui::Painter,
 ) {
-    let (props, texture) = get_tex_for_entry(
-        thumbnail_cache,
-        id,
-        entries,
-        thumbnail_loader,
-        thumb_size,
-        res,
-    );
+    let (props, texture) =
+        get_tex_for_entry(thumbnail_cache, id, coll, thumbnail_loader, thumb_size, res);
     sprite.set_texture(texture, true);
     sprite.set_position((x, y));
     if thumbnail_loader.busy_with().contains(&id) {
@@ -306,7 +518,7 @@ fn draw_thumbnail<'a: 'b, 'b>(
     if Key::LAlt.is_pressed() {
         show_filename = true;
     }
-    if show_filename && let Some(path_string) = entries[&id].path.to_str() {
+    if show_filename && let Some(path_string) = coll.entries[&id].path.to_str() {
         let galley = painter.ctx().fonts_mut(|fonts| {
             fonts.layout(
                 path_string.into(),
@@ -322,6 +534,42 @@ fn draw_thumbnail<'a: 'b, 'b>(
         );
         painter.galley(fname_pos.into(), galley, egui::Color32::WHITE);
     }
+    let en = &coll.entries[&id];
+    if en.favorite || en.rating > 0 {
+        let mut badge = String::new();
+        if en.favorite {
+            badge.push('♥');
+        }
+        badge.extend(std::iter::repeat_n('★', usize::from(en.rating)));
+        let galley = painter.ctx().fonts_mut(|fonts| {
+            fonts.layout_no_wrap(badge, egui::FontId::proportional(14.0), egui::Color32::GOLD)
+        });
+        let pos = egui::pos2(x + 2.0, y + thumb_size as f32 - galley.size().y - 2.0);
+        painter.rect_filled(
+            egui::Rect::from_min_size(pos, galley.size()),
+            1.0,
+            egui::Color32::from_rgba_premultiplied(0, 0, 0, 160),
+        );
+        painter.galley(pos, galley, egui::Color32::GOLD);
+    }
+}
+
+/// Page count badge in the top right corner of a collapsed sequence tile
+fn draw_seq_badge(painter: &egui::Painter, x: f32, y: f32, thumb_size: u32, len: usize) {
+    let galley = painter.ctx().fonts_mut(|fonts| {
+        fonts.layout_no_wrap(
+            format!("⬌ {len}"),
+            egui::FontId::proportional(16.0),
+            egui::Color32::WHITE,
+        )
+    });
+    let pos = egui::pos2(x + thumb_size as f32 - galley.size().x - 4.0, y + 4.0);
+    painter.rect_filled(
+        egui::Rect::from_min_size(pos, galley.size()).expand(2.0),
+        3.0,
+        egui::Color32::from_rgba_premultiplied(20, 40, 100, 200),
+    );
+    painter.galley(pos, galley, egui::Color32::WHITE);
 }
 
 #[derive(Default)]
@@ -333,7 +581,7 @@ pub(in crate::gui) fn handle_event(
     event: Event,
     state: &mut State,
     egui_state: &mut EguiState,
-    coll: &Collection,
+    coll: &mut Collection,
     window: &RenderWindow,
     egui_ctx: &Context,
     preferences: &mut Preferences,
@@ -344,25 +592,44 @@ pub(in crate::gui) fn handle_event(
             if !egui_state.ptr_over_content_area {
                 return;
             }
-            let Some(uid) = state.thumbs_view.entry_at_xy(x, y) else {
+            let pressed = state.thumbs_view.entry_at_xy(x, y);
+            if button == mouse::Button::Left
+                && !Key::LShift.is_pressed()
+                && !Key::LControl.is_pressed()
+            {
+                // Whether this is a click or a rubber band drag is decided on release
+                let pos = (x as f32, y as f32 + state.thumbs_view.y_offset);
+                let idx = state.thumbs_view.abs_thumb_index_at_xy(x, y);
+                state.thumbs_view.extend = None;
+                state.thumbs_view.drag = Some(Drag {
+                    origin: pos,
+                    current: pos,
+                    active: false,
+                    pressed: pressed.map(|id| (id, idx)),
+                    base: coll
+                        .sel_bufs
+                        .current()
+                        .map(|buf| buf.buf.clone())
+                        .unwrap_or_default(),
+                });
+                return;
+            }
+            let Some(uid) = pressed else {
                 return;
             };
             if button == mouse::Button::Left {
+                state.thumbs_view.extend = None;
                 if Key::LShift.is_pressed() {
-                    let Some(sel_buf) = state.sel.current_mut() else {
+                    let Some(sel_buf) = coll.sel_bufs.current_mut() else {
                         egui_state.modal.err("Selection buffer is not accessible");
                         return;
                     };
-                    if sel_buf.contains(&uid) {
-                        sel_buf.buf.retain(|&rhs| rhs != uid);
-                    } else {
-                        sel_buf.buf.push(uid);
-                    }
-                } else if Key::LControl.is_pressed() {
+                    sel_buf.toggle(uid);
+                } else {
                     let curr_thumb_idx = state.thumbs_view.abs_thumb_index_at_xy(x, y);
                     match state.select_a {
                         Some(a) => {
-                            let Some(sel_buf) = state.sel.current_mut() else {
+                            let Some(sel_buf) = coll.sel_bufs.current_mut() else {
                                 egui_state.modal.err("Selection buffer is not accessible");
                                 return;
                             };
@@ -374,30 +641,9 @@ pub(in crate::gui) fn handle_event(
                         }
                         None => state.select_a = Some(curr_thumb_idx),
                     }
-                } else {
-                    let result = try {
-                        if preferences.use_built_in_viewer {
-                            if let Some(id) = state.thumbs_view.entry_at_xy(x, y) {
-                                builtin::open_single_with_others(
-                                    id,
-                                    coll,
-                                    state,
-                                    window,
-                                    state.thumbs_view.abs_thumb_index_at_xy(x, y),
-                                )?;
-                            }
-                        } else {
-                            external::open_single_with_others(coll, uid, preferences)?;
-                        }
-                    };
-                    if let Err(e) = result {
-                        egui_state
-                            .modal
-                            .err(format!("Error opening entries: {e:?}"));
-                    }
                 }
             } else if button == mouse::Button::Right {
-                let entries = match state.sel.current_as_nonempty_id_vec() {
+                let entries = match coll.sel_bufs.current_as_nonempty_id_vec() {
                     Some(sel_uids) => {
                         if sel_uids.contains(&uid) {
                             // If the selection contains the right clicked item, open all selections
@@ -415,6 +661,48 @@ pub(in crate::gui) fn handle_event(
                 egui_state.add_entries_window(entries);
             }
         }
+        Event::MouseButtonReleased {
+            button: mouse::Button::Left,
+            ..
+        } => {
+            let Some(drag) = state.thumbs_view.drag.take() else {
+                return;
+            };
+            // A drag already did its job while it was ongoing
+            if drag.active {
+                return;
+            }
+            let Some((uid, idx)) = drag.pressed else {
+                return;
+            };
+            let result = try {
+                if let Some(seq_id) = state.thumbs_view.collapsed_seq(uid)
+                    && let Some(seq) = coll.sequences.get(&seq_id)
+                {
+                    let start = seq.resume_entry().unwrap_or(uid);
+                    if preferences.use_built_in_viewer {
+                        builtin::open_sequence(state, seq_id, seq, start, window)?;
+                    } else {
+                        external::open_sequence(
+                            seq,
+                            start,
+                            &coll.entries,
+                            &coll.roots,
+                            preferences,
+                        )?;
+                    }
+                } else if preferences.use_built_in_viewer {
+                    builtin::open_single_with_others(uid, coll, state, window, idx)?;
+                } else {
+                    external::open_single_with_others(coll, uid, preferences)?;
+                }
+            };
+            if let Err(e) = result {
+                egui_state
+                    .modal
+                    .err(format!("Error opening entries: {e:?}"));
+            }
+        }
         Event::KeyPressed {
             code, ctrl, shift, ..
         } => {
@@ -423,15 +711,12 @@ pub(in crate::gui) fn handle_event(
             {
                 return;
             }
-            if code == Key::PageDown {
-                state.thumbs_view.y_offset += window.size().y as f32;
-                state.thumbs_view.clamp_bottom(window);
-            } else if code == Key::PageUp {
-                state.thumbs_view.y_offset -= window.size().y as f32;
-                state.thumbs_view.clamp_top();
-            } else if code == Key::Enter {
+            if !ctrl && handle_cursor_key(state, coll, window, code, shift) {
+                return;
+            }
+            if code == Key::Enter {
                 if preferences.use_built_in_viewer {
-                    builtin::on_enter_open(state, window);
+                    builtin::on_enter_open(state, coll, window);
                 } else if let Err(e) = external::on_enter_open(state, coll, preferences) {
                     egui_state.modal.err(format!("Failed to open file(s): {e}"));
                 }
@@ -447,11 +732,16 @@ pub(in crate::gui) fn handle_event(
                 search_next(state, coll, window.size().y);
             } else if code == Key::P {
                 search_prev(state, coll, window.size().y);
+            } else if code == Key::F && shift {
+                let ids = rating_targets(state, coll, window);
+                coll.toggle_favorite(&ids);
             } else if code == Key::F {
                 egui_state.filter_popup.on = true;
+            } else if let Some(rating) = rating_key(code) {
+                let ids = rating_targets(state, coll, window);
+                coll.set_rating(&ids, rating);
             } else if code == Key::C {
-                let mp = window.mouse_position();
-                let Some(uid) = state.thumbs_view.entry_at_xy(mp.x, mp.y) else {
+                let Some(uid) = hovered_or_cursor(state, window) else {
                     return;
                 };
                 if let Err(e) = copy_image_to_clipboard(state, coll, uid) {
@@ -459,25 +749,30 @@ pub(in crate::gui) fn handle_event(
                 }
             } else if code == Key::T {
                 egui_state.tag_window.toggle();
+            } else if code == Key::Q && shift {
+                state.thumbs_view.collapse_seqs ^= true;
+                state
+                    .thumbs_view
+                    .update_from_collection(coll, &state.filter);
+                state.thumbs_view.clamp_bottom(window);
             } else if code == Key::Q {
                 egui_state.sequences_window.on ^= true;
             } else if code == Key::S {
                 state
                     .thumbs_view
-                    .update_from_collection(coll, &state.filter, &state.sel);
+                    .update_from_collection(coll, &state.filter);
             } else if code == Key::R {
                 state.thumbs_view.uids.shuffle(&mut rand::rng());
-            } else if code == Key::Home {
-                if !egui_ctx.egui_wants_keyboard_input() {
-                    state.thumbs_view.y_offset = 0.0;
+            } else if code == Key::Space {
+                let view = &mut state.thumbs_view;
+                if let Some(uid) = view.highlight.and_then(|idx| view.get(idx as usize))
+                    && let Some(buf) = coll.sel_bufs.current_mut()
+                {
+                    view.extend = None;
+                    buf.toggle(uid);
                 }
-            } else if code == Key::End && !egui_ctx.egui_wants_keyboard_input() {
-                // Align the bottom edge of the view with the bottom edge of the last row.
-                // To do align the camera with a bottom edge, we need to subtract the screen
-                // height from it.
-                state.thumbs_view.go_to_bottom(window);
             } else if code == Key::F2
-                && let Some(id_vec) = state.sel.current_as_nonempty_id_vec()
+                && let Some(id_vec) = coll.sel_bufs.current_as_nonempty_id_vec()
             {
                 egui_state.add_entries_window(id_vec.clone());
             } else if code == Key::Escape {
@@ -496,14 +791,105 @@ pub(in crate::gui) fn handle_event(
     }
 }
 
+/// The entry under the mouse pointer, or if there is none, the one under the keyboard cursor
+fn hovered_or_cursor(state: &State, window: &RenderWindow) -> Option<entry::Id> {
+    let mp = window.mouse_position();
+    state.thumbs_view.entry_at_xy(mp.x, mp.y).or_else(|| {
+        let idx = state.thumbs_view.highlight?;
+        state.thumbs_view.get(idx as usize)
+    })
+}
+
+/// The entries that rating and favorite keys apply to.
+///
+/// These are the selected entries, or if nothing is selected, the hovered or highlighted one.
+fn rating_targets(state: &State, coll: &Collection, window: &RenderWindow) -> Vec<entry::Id> {
+    match coll.sel_bufs.current_as_nonempty_id_vec() {
+        Some(ids) => ids.clone(),
+        None => hovered_or_cursor(state, window).into_iter().collect(),
+    }
+}
+
+/// The star rating a number key sets
+pub(super) fn rating_key(code: Key) -> Option<u8> {
+    let rating = match code {
+        Key::Num0 | Key::Numpad0 => 0,
+        Key::Num1 | Key::Numpad1 => 1,
+        Key::Num2 | Key::Numpad2 => 2,
+        Key::Num3 | Key::Numpad3 => 3,
+        Key::Num4 | Key::Numpad4 => 4,
+        Key::Num5 | Key::Numpad5 => 5,
+        _ => return None,
+    };
+    Some(rating)
+}
+
+/// Handle keyboard cursor movement keys.
+///
+/// Holding shift extends the selection from where the cursor was when shift-extending started.
+///
+/// Returns whether `code` was a cursor key.
+fn handle_cursor_key(
+    state: &mut State,
+    coll: &mut Collection,
+    window: &RenderWindow,
+    code: Key,
+    shift: bool,
+) -> bool {
+    let view = &mut state.thumbs_view;
+    let per_row = isize::from(view.thumbs_per_row);
+    let per_page = per_row * (window.size().y / view.thumb_size).max(1) as isize;
+    let mv = match code {
+        Key::Left => CursorMove::By(-1),
+        Key::Right => CursorMove::By(1),
+        Key::Up => CursorMove::By(-per_row),
+        Key::Down => CursorMove::By(per_row),
+        Key::PageUp => CursorMove::By(-per_page),
+        Key::PageDown => CursorMove::By(per_page),
+        Key::Home => CursorMove::First,
+        Key::End => CursorMove::Last,
+        _ => return false,
+    };
+    let prev = view
+        .highlight
+        .map(|idx| idx as usize)
+        .filter(|&idx| idx < view.uids.len());
+    let Some(new) = view.move_cursor(mv, window.size().y) else {
+        return true;
+    };
+    if !shift {
+        view.extend = None;
+        return true;
+    }
+    let Some(buf) = coll.sel_bufs.current_mut() else {
+        dlog!("Current selection buffer inaccessible");
+        return true;
+    };
+    let ext = view.extend.get_or_insert_with(|| ExtendSel {
+        anchor: prev.unwrap_or(new),
+        base: buf.buf.clone(),
+    });
+    let (min, max) = (ext.anchor.min(new), ext.anchor.max(new));
+    let base_set: EntrySet = ext.base.iter().copied().collect();
+    let mut new_sel = ext.base.clone();
+    new_sel.extend(
+        view.uids
+            .iter()
+            .skip(min)
+            .take((max + 1) - min)
+            .filter(|id| !base_set.contains(id)),
+    );
+    buf.buf = new_sel;
+    true
+}
+
 fn copy_image_to_clipboard(
     state: &mut State,
     coll: &Collection,
     uid: entry::Id,
 ) -> anyhow::Result<()> {
     use arboard::ImageData;
-    let imgpath = &coll.entries[&uid].path;
-    let buf = std::fs::read(imgpath)?;
+    let buf = archive::read(&coll.entry_path(&coll.entries[&uid]))?;
     let img = image::load_from_memory(&buf).context("Failed to load image from memory")?;
     let rgba = img.to_rgba8();
     let img_data = ImageData {
@@ -517,27 +903,27 @@ fn copy_image_to_clipboard(
         .context("Failed to copy to clipboard")
 }
 
-pub(in crate::gui) fn select_all(state: &mut State, coll: &Collection) {
-    let ids: Vec<entry::Id> = coll.filter(&state.filter, &state.sel).collect();
-    let Some(buf) = state.sel.current_mut() else {
+pub(in crate::gui) fn select_all(state: &State, coll: &mut Collection) {
+    let ids: Vec<entry::Id> = coll.filter(&state.filter).collect();
+    let Some(buf) = coll.sel_bufs.current_mut() else {
         dlog!("Current selection buffer inacessible");
         return;
     };
     buf.buf = ids;
 }
 
-pub(in crate::gui) fn add_all_to_selection(state: &mut State, coll: &Collection) {
-    let Some(current_buf) = state.sel.current() else {
+pub(in crate::gui) fn add_all_to_selection(state: &State, coll: &mut Collection) {
+    let Some(current_buf) = coll.sel_bufs.current() else {
         dlog!("Current selection buffer inacessible");
         return;
     };
     let mut new_buf = Vec::new();
-    for uid in coll.filter(&state.filter, &state.sel) {
+    for uid in coll.filter(&state.filter) {
         if !current_buf.contains(&uid) {
             new_buf.push(uid);
         }
     }
-    let Some(current_buf_mut) = state.sel.current_mut() else {
+    let Some(current_buf_mut) = coll.sel_bufs.current_mut() else {
         dlog!("Current selection buffer inacessible");
         return;
     };
@@ -560,19 +946,15 @@ pub(in crate::gui) fn search_next(state: &mut State, coll: &Collection, view_hei
 }
 
 fn find_nth(state: &State, coll: &Collection, nth: usize) -> Option<usize> {
+    let inherited = coll.inherited_tag_map();
     state
         .thumbs_view
         .iter()
         .enumerate()
         .filter(|(_, uid)| {
             let en = &coll.entries[uid];
-            en.all_reqs_satisfied(
-                *uid,
-                &state.find_reqs,
-                &coll.tags,
-                &coll.sequences,
-                &state.sel,
-            )
+            let inherited = inherited.get(uid).map_or(&[][..], Vec::as_slice);
+            en.all_reqs_satisfied(*uid, &state.find_reqs, coll, inherited)
         })
         .map(|(i, _)| i)
         .nth(nth)
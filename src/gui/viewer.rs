@@ -1,6 +1,6 @@
 use {
-    super::{Activity, State, thumbnail_loader::imagebuf_to_sf_tex},
-    crate::{collection::Collection, dlog, entry},
+    super::{Activity, State, thumbnail_loader::imagebuf_to_sf_tex, thumbnails_view::rating_key},
+    crate::{archive, collection::Collection, dlog, entry, sequence},
     egui_sf2g::{
         egui,
         sf2g::{
@@ -23,16 +23,55 @@ pub(super) fn draw(
         return;
     }
     let id = state.viewer_state.image_list[state.viewer_state.index];
-    let entry = &coll.entries[&id];
-    match state.viewer_state.image_cache.get(id) {
+    draw_entry(&mut state.viewer_state, window, coll, id, painter);
+    if !state.viewer_state.hide_notes
+        && let Some(en) = coll.entries.get(&id)
+        && !en.notes.is_empty()
+    {
+        draw_notes(&en.notes, window, painter);
+    }
+}
+
+/// Draw notes at the bottom left of the window
+fn draw_notes(notes: &str, window: &RenderWindow, painter: &egui::Painter) {
+    let max_width = (window.size().x as f32 / 2.0).max(200.0);
+    let galley = painter.ctx().fonts_mut(|fonts| {
+        fonts.layout(
+            notes.to_owned(),
+            egui::FontId::proportional(14.0),
+            egui::Color32::WHITE,
+            max_width,
+        )
+    });
+    let pos = egui::pos2(8.0, window.size().y as f32 - galley.size().y - 8.0);
+    painter.rect_filled(
+        egui::Rect::from_min_size(pos, galley.size()).expand(4.0),
+        4.0,
+        egui::Color32::from_rgba_premultiplied(0, 0, 0, 180),
+    );
+    painter.galley(pos, galley, egui::Color32::WHITE);
+}
+
+/// Draw the image of entry `id`, loading it into the image cache if needed
+pub(super) fn draw_entry(
+    viewer_state: &mut ViewerState,
+    window: &mut RenderWindow,
+    coll: &Collection,
+    id: entry::Id,
+    painter: &egui::Painter,
+) {
+    let Some(entry) = coll.entries.get(&id) else {
+        return;
+    };
+    match viewer_state.image_cache.get(id) {
         Some(result) => match result {
             Ok(tex) => {
                 let mut spr = Sprite::with_texture(tex);
                 spr.move_((
-                    state.viewer_state.image_offset.0 as f32,
-                    state.viewer_state.image_offset.1 as f32,
+                    viewer_state.image_offset.0 as f32,
+                    viewer_state.image_offset.1 as f32,
                 ));
-                spr.set_scale((state.viewer_state.scale, state.viewer_state.scale));
+                spr.set_scale((viewer_state.scale, viewer_state.scale));
                 window.draw_sprite(&spr, &RenderStates::DEFAULT);
             }
             Err(e) => {
@@ -46,7 +85,7 @@ pub(super) fn draw(
             }
         },
         None => {
-            let data = match std::fs::read(&entry.path) {
+            let data = match archive::read(&coll.entry_path(entry)) {
                 Ok(data) => data,
                 Err(e) => {
                     dlog!("Error loading image: {e}");
@@ -56,23 +95,32 @@ pub(super) fn draw(
             match image::load_from_memory(&data) {
                 Ok(img) => {
                     let tex = imagebuf_to_sf_tex(img.to_rgba8());
-                    state.viewer_state.image_cache.insert((id, Ok(tex)));
+                    viewer_state.image_cache.insert((id, Ok(tex)));
                 }
                 Err(e) => {
-                    state
-                        .viewer_state
+                    viewer_state
                         .image_cache
                         .insert((id, Err(anyhow::anyhow!(e))));
                 }
             }
-            state.viewer_state.zoom_to_fit(window);
+            viewer_state.zoom_to_fit_entry(id, window);
         }
     }
 }
 
-pub(super) fn handle_event(state: &mut State, event: &Event, window: &RenderWindow) {
+pub(super) fn handle_event(
+    state: &mut State,
+    event: &Event,
+    coll: &mut Collection,
+    window: &RenderWindow,
+) {
     match *event {
         Event::KeyPressed { code, shift, .. } => match code {
+            Key::F => {
+                if let Some(id) = state.viewer_state.shown_entry() {
+                    coll.toggle_favorite(&[id]);
+                }
+            }
             Key::Left => state.viewer_state.prev(window),
             Key::Right => state.viewer_state.next(window),
             Key::Escape => state.activity = Activity::Thumbnails,
@@ -81,7 +129,25 @@ pub(super) fn handle_event(state: &mut State, event: &Event, window: &RenderWind
             Key::Hyphen => state.viewer_state.zoom_out(),
             Key::Delete => state.viewer_state.remove_from_view_list(),
             Key::R => state.viewer_state.zoom_to_fit(window),
-            _ => {}
+            Key::N => state.viewer_state.hide_notes ^= true,
+            Key::V => state.viewer_state.next_variant(coll, window),
+            Key::B => {
+                if let Some(seq) = state
+                    .viewer_state
+                    .seq
+                    .and_then(|id| coll.sequences.get_mut(&id))
+                    && let Some(id) = state.viewer_state.shown_entry()
+                {
+                    seq.add_bookmark(id);
+                }
+            }
+            _ => {
+                if let Some(rating) = rating_key(code)
+                    && let Some(id) = state.viewer_state.shown_entry()
+                {
+                    coll.set_rating(&[id], rating);
+                }
+            }
         },
         Event::MouseButtonPressed {
             button: mouse::Button::Left,
@@ -148,6 +214,10 @@ pub struct ViewerState {
     pub image_list: Vec<entry::Id>,
     pub slideshow_timer_ms: u32,
     pub last_slideshow_instant: Option<Instant>,
+    /// Don't draw the notes of the shown entry
+    pub hide_notes: bool,
+    /// The sequence being viewed, for keeping track of reading progress
+    pub seq: Option<sequence::Id>,
 }
 
 impl ViewerState {
@@ -155,9 +225,12 @@ impl ViewerState {
         self.image_list.get(self.index).copied()
     }
     pub(in crate::gui) fn zoom_to_fit(&mut self, window: &RenderWindow) {
+        let id = self.image_list[self.index];
+        self.zoom_to_fit_entry(id, window);
+    }
+    pub(in crate::gui) fn zoom_to_fit_entry(&mut self, id: entry::Id, window: &RenderWindow) {
         self.scale = 1.0;
         self.image_offset = (0, 0);
-        let id = self.image_list[self.index];
         if let Some(Ok(img)) = self.image_cache.get(id) {
             let img_size = img.size();
             let win_size = window.size();
@@ -169,6 +242,21 @@ impl ViewerState {
             }
         }
     }
+    /// Replace the shown entry with the next one in its group of variants
+    pub(in crate::gui) fn next_variant(&mut self, coll: &Collection, window: &RenderWindow) {
+        let Some(id) = self.shown_entry() else {
+            return;
+        };
+        let variants = coll.variants_of(id);
+        let Some(pos) = variants.iter().position(|v| *v == id) else {
+            return;
+        };
+        let next = variants[(pos + 1) % variants.len()];
+        if next != id {
+            self.image_list[self.index] = next;
+            self.zoom_to_fit(window);
+        }
+    }
     pub(in crate::gui) fn remove_from_view_list(&mut self) {
         self.image_list.remove(self.index);
         self.index = self.index.saturating_sub(1);
@@ -228,6 +316,7 @@ pub fn menu_ui(ui: &mut egui::Ui, state: &mut State, win: &RenderWindow) {
         if ui.button("Zoom to fit (R)").clicked() {
             state.viewer_state.zoom_to_fit(win);
         }
+        ui.checkbox(&mut state.viewer_state.hide_notes, "Hide notes (N)");
         ui.separator();
         if ui.button("Remove from view list (Del)").clicked() {
             state.viewer_state.remove_from_view_list();
@@ -239,6 +328,15 @@ pub fn menu_ui(ui: &mut egui::Ui, state: &mut State, win: &RenderWindow) {
     });
 }
 
+/// Remember the shown entry as the reading progress of the viewed sequence
+pub(crate) fn record_progress(viewer_state: &ViewerState, coll: &mut Collection) {
+    if let Some(seq) = viewer_state.seq.and_then(|id| coll.sequences.get_mut(&id))
+        && let Some(id) = viewer_state.shown_entry()
+    {
+        seq.set_progress(id);
+    }
+}
+
 pub(crate) fn update(state: &mut State, win: &RenderWindow) {
     let timer = state.viewer_state.slideshow_timer_ms;
     if timer != 0 {
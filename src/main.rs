@@ -23,17 +23,27 @@
 )]
 
 mod application;
+mod archive;
+mod cbz;
 mod collection;
+mod constraints;
 mod db;
 mod entry;
 mod entry_utils;
 mod filter_reqs;
 mod folder_scan;
 mod gui;
+mod merge;
+mod notes;
 mod preferences;
+mod relation;
+mod root;
+mod selection;
+mod seq_detect;
 mod sequence;
 mod serialization;
 mod tag;
+mod tag_stats;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
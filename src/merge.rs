@@ -0,0 +1,568 @@
+//! Importing the entries, tags and sequences of another collection into this one
+
+use {
+    crate::{
+        archive,
+        collection::{self, Collection},
+        db::{EntryMap, UidCounter},
+        dlog, entry, root,
+        tag::{self, Tag},
+    },
+    fnv::FnvHashMap,
+    std::{
+        collections::{HashMap, HashSet},
+        hash::{DefaultHasher, Hasher as _},
+        path::{Path, PathBuf},
+        sync::mpsc::{Receiver, TryRecvError},
+    },
+};
+
+/// What importing a source collection would do
+pub struct Plan {
+    /// The collection this plan imports into
+    pub dst: collection::Id,
+    src: Collection,
+    /// Where each source entry ends up
+    entries: EntryMap<Target>,
+    /// Folders to add as roots, for source entries that aren't under any of our roots
+    new_roots: Vec<PathBuf>,
+    /// Source tags that have a name in common with one of ours
+    tag_matches: FnvHashMap<tag::Id, tag::Id>,
+    pub conflicts: Vec<Conflict>,
+    pub stats: Stats,
+}
+
+enum Target {
+    /// The same file as one of our entries
+    Existing(entry::Id),
+    New {
+        place: Place,
+        path: PathBuf,
+    },
+}
+
+/// The root a new entry goes under
+enum Place {
+    Root(Option<root::Id>),
+    /// Index into [`Plan::new_roots`]
+    NewRoot(usize),
+}
+
+#[derive(Default)]
+pub struct Stats {
+    pub matched_by_path: usize,
+    pub matched_by_content: usize,
+    pub new_entries: usize,
+    pub new_tags: usize,
+    pub new_roots: usize,
+    pub sequences: usize,
+    /// New entries left out because their folder overlaps one of our roots
+    pub skipped: usize,
+}
+
+/// A value that differs between a matched pair of entries
+pub struct Conflict {
+    src: entry::Id,
+    pub dst: entry::Id,
+    pub field: Field,
+    pub mine: String,
+    pub theirs: String,
+    /// Whether to take the value of the source collection
+    pub take_theirs: bool,
+}
+
+pub enum Field {
+    Rating,
+    Notes,
+    Prop(String),
+}
+
+impl Field {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Rating => "rating",
+            Self::Notes => "notes",
+            Self::Prop(name) => name,
+        }
+    }
+}
+
+/// Size and hash of the contents of a file
+type ContentKey = (u64, u64);
+
+fn content_key(path: &Path) -> Option<ContentKey> {
+    let data = archive::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&data);
+    Some((data.len() as u64, hasher.finish()))
+}
+
+/// Match source files to destination files with the same contents.
+///
+/// Sizes are compared first, so only files with a size in common get read and hashed.
+fn match_content(
+    src_files: Vec<(entry::Id, PathBuf)>,
+    dst_files: Vec<(entry::Id, PathBuf)>,
+) -> EntryMap<entry::Id> {
+    let src_files: Vec<_> = src_files
+        .into_iter()
+        .filter_map(|(id, path)| Some((id, archive::size(&path).ok()?, path)))
+        .collect();
+    let src_sizes: HashSet<u64> = src_files.iter().map(|(_, size, _)| *size).collect();
+    let mut dst_sizes = HashSet::new();
+    let mut dst_keys: HashMap<ContentKey, entry::Id> = HashMap::new();
+    for (id, path) in dst_files {
+        let Ok(size) = archive::size(&path) else {
+            continue;
+        };
+        if !src_sizes.contains(&size) {
+            continue;
+        }
+        dst_sizes.insert(size);
+        if let Some(key) = content_key(&path) {
+            dst_keys.entry(key).or_insert(id);
+        }
+    }
+    let mut matches = EntryMap::default();
+    for (id, size, path) in src_files {
+        if dst_sizes.contains(&size)
+            && let Some(key) = content_key(&path)
+            && let Some(dst_id) = dst_keys.get(&key)
+        {
+            matches.insert(id, *dst_id);
+        }
+    }
+    matches
+}
+
+/// An analysis whose content comparison is running on a worker thread
+pub struct Pending {
+    /// The collection this analysis imports into
+    pub dst: collection::Id,
+    src: Collection,
+    /// Targets of the source entries under one of our roots
+    entries: EntryMap<Target>,
+    /// Source entries outside of our roots, along with the folder of their source root
+    outside: Vec<(entry::Id, PathBuf)>,
+    /// Folders of all our roots, the primary one included
+    dst_roots: Vec<PathBuf>,
+    matched_by_path: usize,
+    recv: Receiver<EntryMap<entry::Id>>,
+}
+
+pub enum Poll {
+    Running(Pending),
+    Done(Plan),
+    Failed,
+}
+
+/// Start working out how to import `src` (rooted at `src_root`) into `dst`
+/// (with id `dst_id`, rooted at `dst_root`).
+///
+/// Entries are matched by their path relative to one of our roots.
+/// Entries outside our roots are matched by content, and added under a new root if there
+/// is no match.
+pub fn analyze(
+    dst_id: collection::Id,
+    dst: &Collection,
+    dst_root: &Path,
+    src: Collection,
+    src_root: &Path,
+) -> Pending {
+    let mut dst_roots: Vec<(Option<root::Id>, &Path)> = vec![(None, dst_root)];
+    dst_roots.extend(
+        dst.roots
+            .iter()
+            .map(|root| (Some(root.id), root.path.as_path())),
+    );
+    let dst_paths: HashMap<(Option<root::Id>, &Path), entry::Id> = dst
+        .entries
+        .iter()
+        .map(|(id, en)| ((en.root, en.path.as_path()), *id))
+        .collect();
+    let mut entries = EntryMap::default();
+    let mut matched_by_path = 0;
+    let mut outside = Vec::new();
+    let mut src_files = Vec::new();
+    for (id, en) in &src.entries {
+        let abs = src_root.join(src.entry_path(en));
+        let under_dst = dst_roots
+            .iter()
+            .find_map(|(root, path)| Some((*root, abs.strip_prefix(path).ok()?)));
+        let target = match under_dst {
+            Some((root, rel)) => match dst_paths.get(&(root, rel)) {
+                Some(dst_id) => {
+                    matched_by_path += 1;
+                    Target::Existing(*dst_id)
+                }
+                None => Target::New {
+                    place: Place::Root(root),
+                    path: rel.to_owned(),
+                },
+            },
+            None => {
+                outside.push((*id, src_root.join(src.resolve_path(en.root, Path::new("")))));
+                src_files.push((*id, abs));
+                continue;
+            }
+        };
+        entries.insert(*id, target);
+    }
+    // Hashing is expensive, so don't even list our files if there is nothing to compare
+    let dst_files: Vec<(entry::Id, PathBuf)> = if src_files.is_empty() {
+        Vec::new()
+    } else {
+        dst.entries
+            .iter()
+            .map(|(id, en)| (*id, dst_root.join(dst.entry_path(en))))
+            .collect()
+    };
+    let (send, recv) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        if let Err(e) = send.send(match_content(src_files, dst_files)) {
+            dlog!("Failed to send content matches: {e}");
+        }
+    });
+    let dst_roots = dst_roots
+        .iter()
+        .map(|(_, path)| path.to_path_buf())
+        .collect();
+    Pending {
+        dst: dst_id,
+        src,
+        entries,
+        outside,
+        dst_roots,
+        matched_by_path,
+        recv,
+    }
+}
+
+impl Pending {
+    /// Finish the plan against `dst` if the content comparison is done
+    pub fn poll(self, dst: &Collection) -> Poll {
+        match self.recv.try_recv() {
+            Ok(matches) => Poll::Done(self.finish(&matches, dst)),
+            Err(TryRecvError::Empty) => Poll::Running(self),
+            Err(TryRecvError::Disconnected) => Poll::Failed,
+        }
+    }
+    fn finish(self, content_matches: &EntryMap<entry::Id>, dst: &Collection) -> Plan {
+        let Self {
+            dst: dst_id,
+            src,
+            mut entries,
+            outside,
+            dst_roots,
+            matched_by_path,
+            ..
+        } = self;
+        let mut stats = Stats {
+            matched_by_path,
+            ..Stats::default()
+        };
+        let mut unmatched = Vec::new();
+        for (id, root_path) in outside {
+            match content_matches.get(&id) {
+                Some(dst_id) => {
+                    stats.matched_by_content += 1;
+                    entries.insert(id, Target::Existing(*dst_id));
+                }
+                None => unmatched.push((id, root_path)),
+            }
+        }
+        let new_roots = outermost_roots(
+            unmatched.iter().map(|(_, root_path)| root_path.as_path()),
+            &dst_roots,
+        );
+        for (id, root_path) in unmatched {
+            let Some(en) = src.entries.get(&id) else {
+                continue;
+            };
+            let abs = root_path.join(&en.path);
+            let place = new_roots
+                .iter()
+                .enumerate()
+                .find_map(|(idx, new_root)| Some((idx, abs.strip_prefix(new_root).ok()?)));
+            match place {
+                Some((idx, rel)) => {
+                    entries.insert(
+                        id,
+                        Target::New {
+                            place: Place::NewRoot(idx),
+                            path: rel.to_owned(),
+                        },
+                    );
+                }
+                None => stats.skipped += 1,
+            }
+        }
+        stats.new_entries = entries
+            .values()
+            .filter(|target| matches!(target, Target::New { .. }))
+            .count();
+        let mut tag_matches = FnvHashMap::default();
+        for (src_id, src_tag) in &src.tags {
+            let matching = dst
+                .tags
+                .iter()
+                .find(|(_, tag)| tag.names.iter().any(|name| src_tag.names.contains(name)));
+            match matching {
+                Some((dst_id, _)) => {
+                    tag_matches.insert(*src_id, *dst_id);
+                }
+                None => stats.new_tags += 1,
+            }
+        }
+        let mut conflicts = Vec::new();
+        for (src_id, target) in &entries {
+            if let Target::Existing(dst_id) = target
+                && let Some(src_en) = src.entries.get(src_id)
+                && let Some(dst_en) = dst.entries.get(dst_id)
+            {
+                let mut conflict = |field, mine: &str, theirs: &str| {
+                    conflicts.push(Conflict {
+                        src: *src_id,
+                        dst: *dst_id,
+                        field,
+                        mine: mine.to_owned(),
+                        theirs: theirs.to_owned(),
+                        take_theirs: false,
+                    });
+                };
+                if dst_en.rating != 0 && src_en.rating != 0 && dst_en.rating != src_en.rating {
+                    conflict(
+                        Field::Rating,
+                        &dst_en.rating.to_string(),
+                        &src_en.rating.to_string(),
+                    );
+                }
+                let (mine, theirs) = (dst_en.notes.trim(), src_en.notes.trim());
+                if !mine.is_empty() && !theirs.is_empty() && mine != theirs {
+                    conflict(Field::Notes, mine, theirs);
+                }
+                for (key, theirs) in &src_en.props {
+                    if let Some(mine) = dst_en.props.get(key)
+                        && mine != theirs
+                    {
+                        conflict(Field::Prop(key.clone()), mine, theirs);
+                    }
+                }
+            }
+        }
+        conflicts.sort_by_key(|conflict| conflict.dst.0);
+        stats.new_roots = new_roots.len();
+        stats.sequences = src.sequences.len();
+        Plan {
+            dst: dst_id,
+            src,
+            entries,
+            new_roots,
+            tag_matches,
+            conflicts,
+            stats,
+        }
+    }
+}
+
+/// Folders to add as roots for `folders`.
+///
+/// Folders inside another one are covered by it, and ones that overlap one of `existing`
+/// are left out.
+fn outermost_roots<'a>(
+    folders: impl Iterator<Item = &'a Path>,
+    existing: &[PathBuf],
+) -> Vec<PathBuf> {
+    let mut folders: Vec<&Path> = folders.collect();
+    folders.sort_by_key(|path| path.components().count());
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for folder in folders {
+        if !roots.iter().any(|root| folder.starts_with(root))
+            && !existing.iter().any(|root| root::overlaps(folder, root))
+        {
+            roots.push(folder.to_owned());
+        }
+    }
+    roots
+}
+
+/// Carry out an import planned by [`analyze`] into `dst`, which must have the id `dst_id`
+/// the plan was made for
+pub fn apply(
+    dst_id: collection::Id,
+    dst: &mut Collection,
+    dst_root: &Path,
+    plan: Plan,
+    uid_counter: &mut UidCounter,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        plan.dst == dst_id,
+        "The import was planned for a different collection"
+    );
+    let Plan {
+        src,
+        entries,
+        new_roots,
+        mut tag_matches,
+        conflicts,
+        ..
+    } = plan;
+    // Our roots may have changed since the analysis, so check for overlaps again
+    let mut root_paths: Vec<PathBuf> = std::iter::once(dst_root.to_owned())
+        .chain(dst.roots.iter().map(|root| root.path.clone()))
+        .collect();
+    let new_root_ids: Vec<Option<root::Id>> = new_roots
+        .into_iter()
+        .map(|path| {
+            if root_paths.iter().any(|root| root::overlaps(&path, root)) {
+                return None;
+            }
+            root_paths.push(path.clone());
+            Some(dst.add_root(root::default_name(&path), path, uid_counter))
+        })
+        .collect();
+    // Tags
+    for (src_id, src_tag) in &src.tags {
+        match tag_matches.get(src_id) {
+            Some(dst_id) => {
+                let new_names: Vec<String> = src_tag
+                    .names
+                    .iter()
+                    .filter(|name| !dst.tags.values().any(|tag| tag.names.contains(name)))
+                    .cloned()
+                    .collect();
+                if let Some(dst_tag) = dst.tags.get_mut(dst_id) {
+                    dst_tag.names.extend(new_names);
+                }
+            }
+            None => {
+                let tag = Tag {
+                    names: src_tag.names.clone(),
+                    implies: Default::default(),
+                };
+                tag_matches.insert(*src_id, dst.add_new_tag(tag, uid_counter));
+            }
+        }
+    }
+    for (src_id, src_tag) in &src.tags {
+        let Some(dst_tag) = tag_matches.get(src_id).and_then(|id| dst.tags.get_mut(id)) else {
+            continue;
+        };
+        dst_tag.implies.extend(
+            src_tag
+                .implies
+                .iter()
+                .filter_map(|id| tag_matches.get(id))
+                .filter(|id| Some(*id) != tag_matches.get(src_id)),
+        );
+    }
+    let map_tag = |id: &tag::Id| tag_matches.get(id).copied();
+    // Entries
+    let mut entry_map = EntryMap::default();
+    for (src_id, target) in entries {
+        let Some(src_en) = src.entries.get(&src_id) else {
+            continue;
+        };
+        let dst_id = match target {
+            Target::Existing(id) => id,
+            Target::New { place, path } => {
+                let root = match place {
+                    Place::Root(root) => root,
+                    Place::NewRoot(idx) => {
+                        let Some(&Some(id)) = new_root_ids.get(idx) else {
+                            continue;
+                        };
+                        Some(id)
+                    }
+                };
+                dst.add_new_entry(root, path, uid_counter)
+            }
+        };
+        let Some(en) = dst.entries.get_mut(&dst_id) else {
+            continue;
+        };
+        entry_map.insert(src_id, dst_id);
+        en.tags.extend(src_en.tags.iter().filter_map(map_tag));
+        for (tag, value) in &src_en.tag_values {
+            if let Some(tag) = map_tag(tag) {
+                en.tag_values.entry(tag).or_insert(*value);
+            }
+        }
+        en.favorite |= src_en.favorite;
+        if en.rating == 0 {
+            en.rating = src_en.rating;
+        }
+        if en.notes.trim().is_empty() {
+            en.notes.clone_from(&src_en.notes);
+        }
+        for (key, value) in &src_en.props {
+            en.props.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    for conflict in conflicts.iter().filter(|conflict| conflict.take_theirs) {
+        let (Some(src_en), Some(en)) = (
+            src.entries.get(&conflict.src),
+            dst.entries.get_mut(&conflict.dst),
+        ) else {
+            continue;
+        };
+        match &conflict.field {
+            Field::Rating => en.rating = src_en.rating,
+            Field::Notes => en.notes.clone_from(&src_en.notes),
+            Field::Prop(key) => {
+                if let Some(value) = src_en.props.get(key) {
+                    en.props.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    for prop in &src.prop_schema {
+        if !dst.prop_schema.contains(prop) {
+            dst.prop_schema.push(prop.clone());
+        }
+    }
+    for rel in &src.relations {
+        if let (Some(from), Some(to)) = (entry_map.get(&rel.from), entry_map.get(&rel.to)) {
+            dst.add_relation(*from, rel.kind, *to);
+        }
+    }
+    // Sequences
+    let map_entry = |id: &entry::Id| entry_map.get(id).copied();
+    for seq in src.sequences.values() {
+        let seq_entries: Vec<entry::Id> = seq.entries.iter().filter_map(map_entry).collect();
+        if seq_entries.is_empty()
+            || dst
+                .sequences
+                .values()
+                .any(|other| other.name == seq.name && other.entries == seq_entries)
+        {
+            continue;
+        }
+        let names: HashSet<&str> = dst.sequences.values().map(|s| s.name.as_str()).collect();
+        let name = if names.contains(seq.name.as_str()) {
+            format!("{} (imported)", seq.name)
+        } else {
+            seq.name.clone()
+        };
+        let id = dst.add_new_sequence(&name, uid_counter);
+        let Some(new) = dst.sequences.get_mut(&id) else {
+            continue;
+        };
+        new.entries = seq_entries;
+        new.tags = seq.tags.iter().filter_map(map_tag).collect();
+        new.cover = seq.cover.as_ref().and_then(map_entry);
+        new.progress = seq.progress.as_ref().and_then(map_entry);
+        new.bookmarks = seq
+            .bookmarks
+            .iter()
+            .filter_map(|bm| {
+                let mut bm = bm.clone();
+                bm.entry = map_entry(&bm.entry)?;
+                Some(bm)
+            })
+            .collect();
+    }
+    dst.rebuild_note_index();
+    dst.tags_changed();
+    Ok(())
+}
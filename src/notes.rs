@@ -0,0 +1,84 @@
+use {
+    crate::{
+        collection::Entries,
+        db::{EntryMap, EntrySet},
+        entry,
+    },
+    std::collections::HashMap,
+};
+
+/// Token index of entry notes, for narrowing down note searches.
+///
+/// Not serialized, it's rebuilt when the collection is loaded.
+#[derive(Default)]
+pub struct NoteIndex {
+    /// Entries that have a token in their notes
+    tokens: HashMap<String, EntrySet>,
+    /// Tokens of each entry, for removing them when the notes change
+    entry_tokens: EntryMap<Vec<String>>,
+}
+
+impl NoteIndex {
+    pub fn build(entries: &Entries) -> Self {
+        let mut index = Self::default();
+        for (id, en) in entries {
+            index.update(*id, &en.notes);
+        }
+        index
+    }
+    /// Reindex the notes of `id`
+    pub fn update(&mut self, id: entry::Id, notes: &str) {
+        for token in self.entry_tokens.remove(&id).unwrap_or_default() {
+            if let Some(set) = self.tokens.get_mut(&token) {
+                set.remove(&id);
+                if set.is_empty() {
+                    self.tokens.remove(&token);
+                }
+            }
+        }
+        let mut new: Vec<String> = tokens(notes).collect();
+        if new.is_empty() {
+            return;
+        }
+        new.sort_unstable();
+        new.dedup();
+        for token in &new {
+            self.tokens.entry(token.clone()).or_default().insert(id);
+        }
+        self.entry_tokens.insert(id, new);
+    }
+    /// Entries that could have `query` in their notes.
+    ///
+    /// Every token of the query has to appear inside a token of the notes, so this can have
+    /// false positives, but no false negatives.
+    ///
+    /// Returns `None` if the query has no tokens to narrow down with, like `?`.
+    pub fn candidates(&self, query: &str) -> Option<EntrySet> {
+        let mut result: Option<EntrySet> = None;
+        for q_token in tokens(query) {
+            let mut matching = EntrySet::default();
+            for (token, ids) in &self.tokens {
+                if token.contains(&q_token) {
+                    matching.extend(ids.iter().copied());
+                }
+            }
+            result = Some(match result {
+                Some(prev) => prev.intersection(&matching).copied().collect(),
+                None => matching,
+            });
+        }
+        result
+    }
+}
+
+/// Lowercase alphanumeric words of `text`
+fn tokens(text: &str) -> impl Iterator<Item = String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Whether `notes` contain `query`, ignoring case
+pub fn notes_contain(notes: &str, query: &str) -> bool {
+    notes.to_lowercase().contains(query)
+}
@@ -46,6 +46,9 @@ pub struct Preferences {
     pub color_theme: Option<ColorTheme>,
     #[serde(default = "default_sort_pref")]
     pub sort_pref: SortPreference,
+    /// Whether tag suggestions also favor tags common in the entry's directory
+    #[serde(default = "tag_suggestions_by_dir_default")]
+    pub tag_suggestions_by_dir: bool,
 }
 
 fn default_sort_pref() -> SortPreference {
@@ -96,6 +99,10 @@ const fn thumbs_per_row_default() -> u8 {
     5
 }
 
+const fn tag_suggestions_by_dir_default() -> bool {
+    true
+}
+
 impl Preferences {
     pub fn resolve_app(&self, name: &str) -> Option<AppId> {
         self.applications
@@ -192,6 +199,7 @@ impl Default for Preferences {
             thumbs_per_row: thumbs_per_row_default(),
             color_theme: None,
             sort_pref: default_sort_pref(),
+            tag_suggestions_by_dir: tag_suggestions_by_dir_default(),
         }
     }
 }
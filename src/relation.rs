@@ -0,0 +1,82 @@
+use {
+    crate::entry,
+    serde_derive::{Deserialize, Serialize},
+};
+
+/// How one entry relates to another
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Kind {
+    /// Alternate version, crop or edit of the other entry
+    #[default]
+    VariantOf,
+    /// The other entry was made from this one
+    SourceOf,
+    /// Related in some other way. Not directional.
+    Related,
+}
+
+impl Kind {
+    pub const ALL: [Self; 3] = [Self::VariantOf, Self::SourceOf, Self::Related];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::VariantOf => "variant of",
+            Self::SourceOf => "source of",
+            Self::Related => "related to",
+        }
+    }
+    /// Name of the relation as seen from the other end
+    pub fn inverse_name(self) -> &'static str {
+        match self {
+            Self::VariantOf => "has variant",
+            Self::SourceOf => "made from",
+            Self::Related => "related to",
+        }
+    }
+    /// The word used for this kind in the filter language
+    pub fn filter_word(self) -> &'static str {
+        match self {
+            Self::VariantOf => "variant",
+            Self::SourceOf => "source",
+            Self::Related => "related",
+        }
+    }
+    pub fn from_filter_word(word: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.filter_word() == word)
+    }
+}
+
+/// `from` is `kind` `to`, e.g. `from` is a variant of `to`
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Relation {
+    pub from: entry::Id,
+    pub kind: Kind,
+    pub to: entry::Id,
+}
+
+pub type Relations = Vec<Relation>;
+
+impl Relation {
+    pub fn involves(&self, id: entry::Id) -> bool {
+        self.from == id || self.to == id
+    }
+    /// The entry at the other end, if `id` is at one end
+    pub fn other(&self, id: entry::Id) -> Option<entry::Id> {
+        if self.from == id {
+            Some(self.to)
+        } else if self.to == id {
+            Some(self.from)
+        } else {
+            None
+        }
+    }
+    /// Whether this is the same relation as `other`, taking undirected kinds into account
+    pub fn same_as(&self, other: &Self) -> bool {
+        self == other
+            || (self.kind == Kind::Related
+                && other.kind == Kind::Related
+                && self.from == other.to
+                && self.to == other.from)
+    }
+}
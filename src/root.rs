@@ -0,0 +1,53 @@
+//! Additional root folders of a collection.
+//!
+//! The primary root of a collection is the one stored in [`Db::collections`](crate::db::Db).
+//! Entries that belong to an additional root refer to it by id, and their paths are relative
+//! to it instead.
+
+use {
+    crate::db::Uid,
+    serde_derive::{Deserialize, Serialize},
+    std::{
+        borrow::Cow,
+        path::{Path, PathBuf},
+    },
+};
+
+#[derive(Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Id(pub Uid);
+
+#[derive(Serialize, Deserialize)]
+pub struct Root {
+    pub id: Id,
+    pub name: String,
+    /// Absolute path of the folder
+    pub path: PathBuf,
+}
+
+/// Name shown for the primary root
+pub const PRIMARY_NAME: &str = "Primary";
+
+/// Name for a new root at `path`: the name of the folder
+pub fn default_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.to_string_lossy().into_owned(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Whether `a` and `b` are the same folder, or one is inside the other.
+///
+/// Overlapping roots would list the same files more than once.
+pub fn overlaps(a: &Path, b: &Path) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+/// Path to access `path` of `root` with, where `None` is the primary root.
+///
+/// The working directory is the primary root, so those paths are usable as they are.
+pub fn resolve<'p>(roots: &[Root], root: Option<Id>, path: &'p Path) -> Cow<'p, Path> {
+    match root.and_then(|id| roots.iter().find(|r| r.id == id)) {
+        Some(root) => root.path.join(path).into(),
+        None => path.into(),
+    }
+}
@@ -0,0 +1,182 @@
+use {
+    crate::{db::EntrySet, entry},
+    anyhow::Context as _,
+    serde_derive::{Deserialize, Serialize},
+};
+
+const CURRENT_INACCESSIBLE: &str = "Current selection buffer inaccessible";
+
+/// A named, unordered set of entries.
+///
+/// Selection buffers are saved along with the collection, so they can also serve as
+/// lightweight albums.
+#[derive(Serialize, Deserialize)]
+pub struct SelectionBuf {
+    pub buf: Vec<entry::Id>,
+    pub name: String,
+}
+
+impl SelectionBuf {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            buf: Vec::new(),
+            name: name.into(),
+        }
+    }
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+    pub fn extend(&mut self, iter: impl IntoIterator<Item = entry::Id>) {
+        self.buf.extend(iter);
+    }
+    pub fn as_vec(&self) -> &Vec<entry::Id> {
+        &self.buf
+    }
+    pub fn remove(&mut self, idx: usize) {
+        self.buf.remove(idx);
+    }
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+    pub fn contains(&self, id: &entry::Id) -> bool {
+        self.buf.contains(id)
+    }
+    /// Select `id` if it's not selected, deselect it otherwise
+    pub fn toggle(&mut self, id: entry::Id) {
+        if self.contains(&id) {
+            self.buf.retain(|&rhs| rhs != id);
+        } else {
+            self.buf.push(id);
+        }
+    }
+    fn id_set(&self) -> EntrySet {
+        self.buf.iter().copied().collect()
+    }
+}
+
+/// An operation that modifies the current selection buffer
+#[derive(Clone, Copy)]
+pub enum SetOp {
+    /// Add the entries of the buffer at this index
+    Union(usize),
+    /// Keep only the entries that are also in the buffer at this index
+    Intersection(usize),
+    /// Remove the entries that are in the buffer at this index
+    Difference(usize),
+    /// Select everything in the current filter result that isn't selected, and vice versa
+    Invert,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SelectionBufs {
+    pub(crate) current: usize,
+    pub(crate) bufs: Vec<SelectionBuf>,
+}
+
+impl Default for SelectionBufs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SelectionBufs {
+    pub fn new() -> Self {
+        Self {
+            current: 0,
+            bufs: vec![SelectionBuf::new("sel-1")],
+        }
+    }
+    pub fn current(&self) -> Option<&SelectionBuf> {
+        self.bufs.get(self.current)
+    }
+    pub fn current_mut(&mut self) -> Option<&mut SelectionBuf> {
+        self.bufs.get_mut(self.current)
+    }
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(&mut SelectionBuf)) {
+        for buf in &mut self.bufs {
+            f(buf);
+        }
+    }
+    pub fn any(&self, f: impl FnMut(&SelectionBuf) -> bool) -> bool {
+        self.bufs.iter().any(f)
+    }
+    pub fn add_buf(&mut self, name: impl Into<String>) {
+        self.bufs.push(SelectionBuf::new(name));
+    }
+    pub(crate) fn n_selected(&self) -> usize {
+        self.current().map_or(0, SelectionBuf::len)
+    }
+    pub(crate) fn none_selected(&self) -> bool {
+        self.n_selected() == 0
+    }
+    /// Returns the currently active selected-ids vec, if any, if not empty
+    pub(crate) fn current_as_nonempty_id_vec(&self) -> Option<&Vec<entry::Id>> {
+        self.current()
+            .filter(|buf| !buf.buf.is_empty())
+            .map(SelectionBuf::as_vec)
+    }
+    pub(crate) fn selected_ids_iter(&self) -> impl Iterator<Item = &entry::Id> {
+        match self.current() {
+            Some(buf) => buf.buf.iter(),
+            None => [].iter(),
+        }
+    }
+    pub(crate) fn current_contains(&self, id: &entry::Id) -> bool {
+        self.current().is_some_and(|buf| buf.contains(id))
+    }
+    pub(crate) fn clear_current(&mut self) {
+        if let Some(current) = self.current_mut() {
+            current.clear();
+        }
+    }
+    /// Names of all the buffers other than the current one, along with their indices
+    pub(crate) fn others(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.bufs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != self.current)
+            .map(|(i, buf)| (i, buf.name.as_str()))
+    }
+    /// Apply `op` to the current buffer.
+    ///
+    /// `filtered` is the current filter result, which is the universe that [`SetOp::Invert`]
+    /// inverts against.
+    pub(crate) fn apply(
+        &mut self,
+        op: SetOp,
+        filtered: impl IntoIterator<Item = entry::Id>,
+    ) -> anyhow::Result<()> {
+        match op {
+            SetOp::Union(idx) => {
+                let other = self.buf_at(idx)?.buf.clone();
+                let current = self.current_mut().context(CURRENT_INACCESSIBLE)?;
+                let current_set = current.id_set();
+                current.extend(other.into_iter().filter(|id| !current_set.contains(id)));
+            }
+            SetOp::Intersection(idx) => {
+                let other_set = self.buf_at(idx)?.id_set();
+                let current = self.current_mut().context(CURRENT_INACCESSIBLE)?;
+                current.buf.retain(|id| other_set.contains(id));
+            }
+            SetOp::Difference(idx) => {
+                let other_set = self.buf_at(idx)?.id_set();
+                let current = self.current_mut().context(CURRENT_INACCESSIBLE)?;
+                current.buf.retain(|id| !other_set.contains(id));
+            }
+            SetOp::Invert => {
+                let current = self.current_mut().context(CURRENT_INACCESSIBLE)?;
+                let current_set = current.id_set();
+                current.buf = filtered
+                    .into_iter()
+                    .filter(|id| !current_set.contains(id))
+                    .collect();
+            }
+        }
+        Ok(())
+    }
+    fn buf_at(&self, idx: usize) -> anyhow::Result<&SelectionBuf> {
+        self.bufs
+            .get(idx)
+            .with_context(|| format!("No selection buffer at index {idx}"))
+    }
+}
@@ -0,0 +1,95 @@
+use {
+    crate::{collection::Collection, db::EntrySet, entry, root},
+    std::{collections::HashMap, path::PathBuf},
+};
+
+/// Minimum number of entries for a group to be proposed as a sequence
+const MIN_LEN: usize = 2;
+
+/// A proposed sequence, found from the numbering of filenames
+pub struct Candidate {
+    pub name: String,
+    /// Directory the entries are in
+    pub dir: PathBuf,
+    /// Entries in natural order
+    pub entries: Vec<entry::Id>,
+}
+
+/// Entries with the same directory, the same text around the counter, and the same extension
+/// belong to the same group.
+#[derive(Hash, PartialEq, Eq)]
+struct GroupKey {
+    root: Option<root::Id>,
+    dir: PathBuf,
+    stem: String,
+    suffix: String,
+}
+
+/// Split `file_stem` around its last run of digits into `(stem, suffix)`
+fn split_counter(file_stem: &str) -> Option<(&str, &str)> {
+    let end = file_stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = file_stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |pos| pos + 1);
+    Some((&file_stem[..start], &file_stem[end..]))
+}
+
+/// Group entries that aren't part of any sequence yet by their filename numbering
+pub fn detect(coll: &Collection) -> Vec<Candidate> {
+    let in_sequence: EntrySet = coll
+        .sequences
+        .values()
+        .flat_map(|seq| seq.entries.iter().copied())
+        .collect();
+    let mut groups: HashMap<GroupKey, Vec<entry::Id>> = HashMap::new();
+    for (id, en) in &coll.entries {
+        if in_sequence.contains(id) {
+            continue;
+        }
+        // Only look for the counter in the stem, as extensions like mp4 have digits too
+        let Some(file_stem) = en.path.file_stem() else {
+            continue;
+        };
+        let file_stem = file_stem.to_string_lossy();
+        let Some((stem, suffix)) = split_counter(&file_stem) else {
+            continue;
+        };
+        let mut suffix = suffix.to_owned();
+        if let Some(ext) = en.path.extension() {
+            suffix.push('.');
+            suffix.push_str(&ext.to_string_lossy());
+        }
+        let key = GroupKey {
+            root: en.root,
+            dir: en.path.parent().map(PathBuf::from).unwrap_or_default(),
+            stem: stem.to_owned(),
+            suffix,
+        };
+        groups.entry(key).or_default().push(*id);
+    }
+    let mut candidates: Vec<Candidate> = groups
+        .into_iter()
+        .filter(|(_, ids)| ids.len() >= MIN_LEN)
+        .map(|(key, mut ids)| {
+            coll.sort_naturally(&mut ids);
+            Candidate {
+                name: candidate_name(&key),
+                dir: key.dir,
+                entries: ids,
+            }
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.dir.cmp(&b.dir).then_with(|| a.name.cmp(&b.name)));
+    candidates
+}
+
+/// The stem without trailing separators, or the directory name if the files are just numbers
+fn candidate_name(key: &GroupKey) -> String {
+    let stem = key.stem.trim_end_matches(['_', '-', ' ', '.', '#']);
+    if !stem.is_empty() {
+        return stem.to_owned();
+    }
+    key.dir
+        .file_name()
+        .map_or_else(|| "Untitled".into(), |name| name.to_string_lossy().into())
+}
@@ -1,5 +1,8 @@
 use {
-    crate::{db::Uid, entry},
+    crate::{
+        db::{EntrySet, Uid},
+        entry, tag,
+    },
     serde_derive::{Deserialize, Serialize},
 };
 
@@ -8,6 +11,24 @@ use {
 pub struct Sequence {
     pub name: String,
     pub entries: Vec<entry::Id>,
+    /// Tags that the entries of the sequence inherit
+    #[serde(default)]
+    pub tags: Vec<tag::Id>,
+    /// Entry shown for the sequence when sequences are collapsed
+    #[serde(default)]
+    pub cover: Option<entry::Id>,
+    /// Last entry viewed in the built-in viewer
+    #[serde(default)]
+    pub progress: Option<entry::Id>,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// A named position in a sequence
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Bookmark {
+    pub name: String,
+    pub entry: entry::Id,
 }
 
 #[derive(Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Debug)]
@@ -52,6 +73,7 @@ impl Sequence {
         if let Some(pos) = self.entries.iter().position(|&uid| uid == id) {
             self.entries.remove(pos);
         }
+        self.bookmarks.retain(|bm| bm.entry != id);
     }
 
     pub(crate) fn entry_uids_wrapped_from(&self, img_uid: entry::Id) -> Option<Vec<entry::Id>> {
@@ -62,7 +84,93 @@ impl Sequence {
         }
         Some(uids)
     }
+    pub(crate) fn reverse(&mut self) {
+        self.entries.reverse();
+    }
+    /// Remove repeated entries, keeping the first occurrence. Returns how many were removed.
+    pub(crate) fn dedupe(&mut self) -> usize {
+        let before = self.entries.len();
+        let mut seen = EntrySet::default();
+        self.entries.retain(|id| seen.insert(*id));
+        before - self.entries.len()
+    }
+    /// Number of entries that appear more than once, not counting their first occurrence
+    pub(crate) fn n_duplicates(&self) -> usize {
+        let mut seen = EntrySet::default();
+        self.entries.iter().filter(|id| !seen.insert(**id)).count()
+    }
+    /// The chosen cover if it's still part of the sequence, otherwise the first entry
+    pub(crate) fn cover(&self) -> Option<entry::Id> {
+        self.cover
+            .filter(|id| self.contains_entry(*id))
+            .or_else(|| self.entries.first().copied())
+    }
+    /// Where to continue reading from: the last viewed entry, or the first one
+    pub(crate) fn resume_entry(&self) -> Option<entry::Id> {
+        self.progress
+            .filter(|id| self.contains_entry(*id))
+            .or_else(|| self.entries.first().copied())
+    }
+    /// Remember `id` as the last viewed entry. Returns whether anything changed.
+    pub(crate) fn set_progress(&mut self, id: entry::Id) -> bool {
+        if self.progress == Some(id) || !self.contains_entry(id) {
+            return false;
+        }
+        self.progress = Some(id);
+        true
+    }
+    /// 1-based position of the last viewed entry
+    pub(crate) fn progress_pos(&self) -> Option<usize> {
+        let progress = self.progress?;
+        self.entries
+            .iter()
+            .position(|id| *id == progress)
+            .map(|pos| pos + 1)
+    }
+    /// Reading was started, but the last entry wasn't reached yet
+    pub(crate) fn is_unfinished(&self) -> bool {
+        self.progress_pos()
+            .is_some_and(|pos| pos < self.entries.len())
+    }
+    pub(crate) fn add_bookmark(&mut self, entry: entry::Id) {
+        if self.bookmarks.iter().any(|bm| bm.entry == entry) {
+            return;
+        }
+        let pos = self
+            .entries
+            .iter()
+            .position(|id| *id == entry)
+            .map_or(0, |pos| pos + 1);
+        self.bookmarks.push(Bookmark {
+            name: format!("Page {pos}"),
+            entry,
+        });
+    }
+    pub(crate) fn add_tag(&mut self, tag: tag::Id) {
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+    }
+    pub(crate) fn replace_tag(&mut self, replace: tag::Id, with: tag::Id) {
+        if self.tags.contains(&with) {
+            self.tags.retain(|tag| *tag != replace);
+        } else if let Some(tag) = self.tags.iter_mut().find(|tag| **tag == replace) {
+            *tag = with;
+        }
+    }
     pub(crate) fn contains_entry(&self, id: entry::Id) -> bool {
         self.entries.contains(&id)
     }
+    /// Whether `query` refers to this sequence by name.
+    ///
+    /// Case insensitive, and underscores in `query` can stand for spaces,
+    /// since filter words can't contain whitespace.
+    pub(crate) fn name_matches(&self, query: &str) -> bool {
+        self.name.len() == query.len()
+            && self
+                .name
+                .chars()
+                .zip(query.chars())
+                .all(|(a, b)| a.eq_ignore_ascii_case(&b) || (a == ' ' && b == '_'))
+    }
 }
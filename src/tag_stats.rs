@@ -0,0 +1,272 @@
+use {
+    crate::{
+        collection::{Collection, Entries, Tags, TagsExt},
+        db::{EntryMap, TagSet},
+        entry, tag,
+    },
+    fnv::FnvHashMap,
+    std::{collections::HashMap, path::PathBuf},
+};
+
+type TagCounts = FnvHashMap<tag::Id, u32>;
+
+/// How much directory statistics weigh compared to co-occurrence statistics
+const DIR_WEIGHT: f32 = 0.5;
+
+/// The tags and directory of an entry, as counted by [`CoOccurrence`]
+struct Snapshot {
+    tags: Vec<tag::Id>,
+    dir: PathBuf,
+}
+
+/// Tag co-occurrence statistics, used for suggesting tags.
+///
+/// Only the entries that changed since the last update are recounted.
+#[derive(Default)]
+pub struct CoOccurrence {
+    snapshots: EntryMap<Snapshot>,
+    /// How many entries have a tag
+    tag_counts: TagCounts,
+    /// How many entries have both tags. Symmetric.
+    co_counts: FnvHashMap<tag::Id, TagCounts>,
+    /// How many entries in a directory have a tag
+    dir_tag_counts: HashMap<PathBuf, TagCounts>,
+    /// How many entries a directory has
+    dir_entry_counts: HashMap<PathBuf, u32>,
+    /// Revision of the collection at the last update
+    revision: Option<(u64, u64)>,
+}
+
+impl CoOccurrence {
+    /// Bring the statistics up to date with `entries`
+    pub fn update(&mut self, entries: &Entries) {
+        let removed: Vec<_> = self
+            .snapshots
+            .keys()
+            .filter(|id| !entries.contains_key(id))
+            .copied()
+            .collect();
+        for id in removed {
+            if let Some(snap) = self.snapshots.remove(&id) {
+                self.count(&snap, false);
+            }
+        }
+        for (id, en) in entries {
+            let dir = en.path.parent().unwrap_or(en.path.as_path());
+            if let Some(snap) = self.snapshots.get(id)
+                && snap.dir == dir
+                && snap.tags.len() == en.tags.len()
+                && snap.tags.iter().all(|tag| en.tags.contains(tag))
+            {
+                continue;
+            }
+            let new = Snapshot {
+                tags: en.tags.iter().copied().collect(),
+                dir: dir.to_owned(),
+            };
+            self.count(&new, true);
+            if let Some(old) = self.snapshots.insert(*id, new) {
+                self.count(&old, false);
+            }
+        }
+    }
+    /// Bring the statistics up to date with `entries`, unless they are already up to date
+    /// with `revision`
+    pub fn update_at(&mut self, entries: &Entries, revision: (u64, u64)) {
+        if self.revision != Some(revision) {
+            self.update(entries);
+            self.revision = Some(revision);
+        }
+    }
+    /// Add (or subtract) the counts of a snapshot
+    fn count(&mut self, snap: &Snapshot, add: bool) {
+        let adjust = |n: &mut u32| {
+            if add {
+                *n += 1;
+            } else {
+                *n = n.saturating_sub(1);
+            }
+        };
+        adjust(self.dir_entry_counts.entry(snap.dir.clone()).or_default());
+        let dir_counts = self.dir_tag_counts.entry(snap.dir.clone()).or_default();
+        for &tag in &snap.tags {
+            adjust(self.tag_counts.entry(tag).or_default());
+            adjust(dir_counts.entry(tag).or_default());
+            let co = self.co_counts.entry(tag).or_default();
+            for &other in &snap.tags {
+                if other != tag {
+                    adjust(co.entry(other).or_default());
+                }
+            }
+        }
+    }
+    /// Tags suggested for `ids`, best first.
+    ///
+    /// Tags are ranked by how often they appear together with the tags the entries already have,
+    /// and if `by_dir` is true, by how common they are in the directories of the entries.
+    pub fn suggest(
+        &self,
+        ids: &[entry::Id],
+        entries: &Entries,
+        tags: &Tags,
+        by_dir: bool,
+        limit: usize,
+    ) -> Vec<tag::Id> {
+        let mut scores: FnvHashMap<tag::Id, f32> = FnvHashMap::default();
+        let mut have_all: Option<TagSet> = None;
+        for id in ids {
+            let Some(en) = entries.get(id) else {
+                continue;
+            };
+            let have = implied_closure(en.tags.iter().copied(), tags);
+            for tag in &en.tags {
+                let Some(co) = self.co_counts.get(tag) else {
+                    continue;
+                };
+                let total = self.tag_counts.get(tag).copied().unwrap_or(1).max(1) as f32;
+                for (other, &n) in co {
+                    if !have.contains(other) {
+                        *scores.entry(*other).or_default() += n as f32 / total;
+                    }
+                }
+            }
+            if by_dir
+                && let Some(dir) = en.path.parent()
+                && let Some(dir_counts) = self.dir_tag_counts.get(dir)
+            {
+                let total = self.dir_entry_counts.get(dir).copied().unwrap_or(1).max(1) as f32;
+                for (other, &n) in dir_counts {
+                    if !have.contains(other) {
+                        *scores.entry(*other).or_default() += DIR_WEIGHT * n as f32 / total;
+                    }
+                }
+            }
+            have_all = Some(match have_all {
+                Some(prev) => prev.intersection(&have).copied().collect(),
+                None => have,
+            });
+        }
+        let have_all = have_all.unwrap_or_default();
+        let mut ranked: Vec<_> = scores
+            .into_iter()
+            .filter(|(tag, score)| {
+                *score > 0.0 && !have_all.contains(tag) && tags.contains_key(tag)
+            })
+            .collect();
+        ranked.sort_by(|(a_tag, a), (b_tag, b)| b.total_cmp(a).then(a_tag.0.cmp(&b_tag.0)));
+        ranked.truncate(limit);
+        ranked.into_iter().map(|(tag, _)| tag).collect()
+    }
+}
+
+/// The given tags, along with every tag they imply, directly or indirectly
+pub(crate) fn implied_closure(start: impl IntoIterator<Item = tag::Id>, tags: &Tags) -> TagSet {
+    let mut set = TagSet::default();
+    let mut stack: Vec<_> = start.into_iter().collect();
+    while let Some(id) = stack.pop() {
+        if set.insert(id)
+            && let Some(tag) = tags.get(&id)
+        {
+            stack.extend(tag.implies.iter().copied());
+        }
+    }
+    set
+}
+
+/// How many entries use a tag
+#[derive(Default, Clone, Copy)]
+pub struct TagUsage {
+    /// Entries that have the tag themselves
+    pub direct: u32,
+    /// Entries that only have the tag because one of their tags implies it
+    pub implied: u32,
+}
+
+impl TagUsage {
+    pub fn total(self) -> u32 {
+        self.direct + self.implied
+    }
+}
+
+/// Usage counts of every tag in the collection
+pub fn usage_counts(coll: &Collection) -> FnvHashMap<tag::Id, TagUsage> {
+    let mut counts: FnvHashMap<tag::Id, TagUsage> = coll
+        .tags
+        .keys()
+        .map(|id| (*id, TagUsage::default()))
+        .collect();
+    for en in coll.entries.values() {
+        for id in implied_closure(en.tags.iter().copied(), &coll.tags) {
+            let usage = counts.entry(id).or_default();
+            if en.tags.contains(&id) {
+                usage.direct += 1;
+            } else {
+                usage.implied += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Two different tags with names that are suspiciously close to each other
+pub struct SimilarPair {
+    pub a: tag::Id,
+    pub b: tag::Id,
+    /// Edit distance between the closest names of the two tags
+    pub distance: usize,
+}
+
+/// Find pairs of tags that have names within `max_distance` edits of each other
+pub fn similar_names(tags: &Tags, max_distance: usize) -> Vec<SimilarPair> {
+    let names: Vec<(tag::Id, Vec<char>)> = tags
+        .iter()
+        .flat_map(|(id, tag)| {
+            tag.names
+                .iter()
+                .map(|name| (*id, name.to_lowercase().chars().collect()))
+        })
+        .collect();
+    let mut pairs: FnvHashMap<(tag::Id, tag::Id), usize> = FnvHashMap::default();
+    for (i, (a_id, a_name)) in names.iter().enumerate() {
+        for (b_id, b_name) in names.iter().skip(i + 1) {
+            if a_id == b_id || a_name.len().abs_diff(b_name.len()) > max_distance {
+                continue;
+            }
+            let distance = levenshtein(a_name, b_name);
+            if distance > max_distance {
+                continue;
+            }
+            let key = if a_id.0 < b_id.0 {
+                (*a_id, *b_id)
+            } else {
+                (*b_id, *a_id)
+            };
+            let min = pairs.entry(key).or_insert(distance);
+            *min = (*min).min(distance);
+        }
+    }
+    let mut pairs: Vec<_> = pairs
+        .into_iter()
+        .map(|((a, b), distance)| SimilarPair { a, b, distance })
+        .collect();
+    pairs.sort_by(|lhs, rhs| {
+        lhs.distance
+            .cmp(&rhs.distance)
+            .then_with(|| tags.first_name_of(&lhs.a).cmp(&tags.first_name_of(&rhs.a)))
+    });
+    pairs
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let subst = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = subst.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}